@@ -0,0 +1,286 @@
+use crate::prelude::*;
+use std::f32::consts::PI;
+
+// timings of the animations, in seconds. Turns wait for running animations at most max_block
+#[derive(Resource)]
+pub struct AnimationSettings {
+    pub enabled: bool,
+    pub move_time: f32,
+    pub bump_time: f32,
+    pub flash_time: f32,
+    pub damage_text_time: f32,
+    pub max_block: f32,
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            move_time: 0.08,
+            bump_time: 0.15,
+            flash_time: 0.2,
+            damage_text_time: 0.8,
+            max_block: 0.15,
+        }
+    }
+}
+
+// sent by combat for every hit, so it can be animated
+#[derive(Event)]
+pub struct AttackEvent {
+    pub attacker: Entity,
+    pub victim: Entity,
+    pub from: Position,
+    pub to: Position,
+    pub damage: i32,
+    // the victim died and is going away
+    pub killed: bool,
+}
+
+// slides the transform between two tiles
+#[derive(Component)]
+pub struct Tween {
+    pub from: Vec3,
+    pub to: Vec3,
+    pub elapsed: f32,
+}
+
+// nudges the attacker towards its victim and back
+#[derive(Component)]
+pub struct Bump {
+    base: Vec3,
+    direction: Vec3,
+    elapsed: f32,
+}
+
+// tints a damaged sprite for a moment
+#[derive(Component)]
+pub struct HitFlash {
+    color: Color,
+    elapsed: f32,
+}
+
+// damage number floating up from the victim
+#[derive(Component)]
+struct DamageText {
+    elapsed: f32,
+}
+
+// how far the bump goes, in tiles
+const BUMP_DISTANCE: f32 = 0.3;
+const FLASH_COLOR: Color = Color::WHITE;
+
+fn start_attack_animations(
+    mut commands: Commands,
+    mut attacks: EventReader<AttackEvent>,
+    settings: Res<AnimationSettings>,
+    font_manager: Res<FontManager>,
+    primary_query: Query<&Window, With<PrimaryWindow>>,
+    mut sprites_q: Query<(
+        &Transform,
+        &mut TextureAtlasSprite,
+        Option<&HitFlash>,
+        Option<&Tween>,
+    )>,
+) {
+    if !settings.enabled {
+        attacks.clear();
+        return;
+    }
+    let tile = match primary_query.get_single() {
+        Ok(window) => Vec2::new(
+            window.width() / SCREEN_WIDTH as f32,
+            window.height() / SCREEN_HEIGHT as f32,
+        ),
+        Err(_) => return,
+    };
+
+    for attack in attacks.read() {
+        let direction = Vec3::new(
+            (attack.to.x - attack.from.x).signum() as f32 * tile.x,
+            (attack.to.y - attack.from.y).signum() as f32 * tile.y,
+            0.0,
+        );
+        if let Ok((transform, _, _, tween)) = sprites_q.get(attack.attacker) {
+            // a move still playing is cut short, the bump starts where it ends
+            let base = tween.map_or(transform.translation, |tween| tween.to);
+            commands
+                .entity(attack.attacker)
+                .remove::<Tween>()
+                .insert(Bump {
+                    base,
+                    direction,
+                    elapsed: 0.0,
+                });
+        }
+
+        // the damage number starts over the victim, even if it just died
+        let victim = match attack.killed {
+            true => Err(()),
+            false => sprites_q.get_mut(attack.victim).map_err(|_| ()),
+        };
+        let victim_translation = match victim {
+            Ok((transform, mut sprite, flash, _)) => {
+                // an ongoing flash already knows the real color
+                let color = flash.map_or(sprite.color, |flash| flash.color);
+                sprite.color = FLASH_COLOR.with_a(sprite.color.a());
+                commands.entity(attack.victim).insert(HitFlash {
+                    color,
+                    elapsed: 0.0,
+                });
+                transform.translation
+            }
+            Err(_) => convert_translation(attack.to, tile),
+        };
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    format!("-{}", attack.damage),
+                    TextStyle {
+                        font: font_manager.font.clone(),
+                        font_size: tile.y,
+                        color: Color::RED,
+                    },
+                ),
+                transform: Transform::from_translation(
+                    victim_translation + Vec3::new(0.0, tile.y / 2.0, 10.0),
+                ),
+                ..Default::default()
+            },
+            DamageText { elapsed: 0.0 },
+        ));
+    }
+}
+
+// world translation of a tile, the same one position_translation would give
+fn convert_translation(pos: Position, tile: Vec2) -> Vec3 {
+    Vec3::new(
+        convert_pos(
+            pos.x as f32,
+            tile.x * SCREEN_WIDTH as f32,
+            SCREEN_WIDTH as f32,
+        ),
+        convert_pos(
+            (pos.y + UI_HEIGHT / 2) as f32,
+            tile.y * SCREEN_HEIGHT as f32,
+            SCREEN_HEIGHT as f32,
+        ),
+        pos.z as f32,
+    )
+}
+
+fn animate(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<AnimationSettings>,
+    primary_query: Query<&Window, With<PrimaryWindow>>,
+    mut tweens_q: Query<(Entity, &mut Tween, &mut Transform), (Without<Bump>, Without<DamageText>)>,
+    mut bumps_q: Query<(Entity, &mut Bump, &mut Transform), (Without<Tween>, Without<DamageText>)>,
+    mut flashes_q: Query<(Entity, &mut HitFlash, &mut TextureAtlasSprite)>,
+    mut texts_q: Query<(Entity, &mut DamageText, &mut Transform, &mut Text)>,
+) {
+    let delta = time.delta_seconds();
+
+    for (entity, mut tween, mut transform) in tweens_q.iter_mut() {
+        tween.elapsed += delta;
+        let t = f32::min(tween.elapsed / settings.move_time, 1.0);
+        transform.translation = tween.from.lerp(tween.to, t);
+        if t >= 1.0 {
+            commands.entity(entity).remove::<Tween>();
+        }
+    }
+
+    for (entity, mut bump, mut transform) in bumps_q.iter_mut() {
+        bump.elapsed += delta;
+        let t = f32::min(bump.elapsed / settings.bump_time, 1.0);
+        transform.translation = bump.base + bump.direction * BUMP_DISTANCE * (t * PI).sin();
+        if t >= 1.0 {
+            commands.entity(entity).remove::<Bump>();
+        }
+    }
+
+    for (entity, mut flash, mut sprite) in flashes_q.iter_mut() {
+        flash.elapsed += delta;
+        if flash.elapsed >= settings.flash_time {
+            // keep the alpha, it belongs to the lighting
+            sprite.color = flash.color.with_a(sprite.color.a());
+            commands.entity(entity).remove::<HitFlash>();
+        }
+    }
+
+    let rise = primary_query
+        .get_single()
+        .map_or(0.0, |window| window.height() / SCREEN_HEIGHT as f32);
+    for (entity, mut text, mut transform, mut content) in texts_q.iter_mut() {
+        text.elapsed += delta;
+        let t = text.elapsed / settings.damage_text_time;
+        transform.translation.y += rise * delta / settings.damage_text_time;
+        for section in content.sections.iter_mut() {
+            section.style.color.set_a(1.0 - t.min(1.0));
+        }
+        if t >= 1.0 {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+// any key press jumps to the end of every running animation
+fn skip_animations(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut tweens_q: Query<(Entity, &Tween, &mut Transform), Without<Bump>>,
+    mut bumps_q: Query<(Entity, &Bump, &mut Transform), Without<Tween>>,
+    mut flashes_q: Query<(Entity, &HitFlash, &mut TextureAtlasSprite)>,
+    texts_q: Query<Entity, With<DamageText>>,
+) {
+    if keyboard_input.get_just_pressed().next().is_none() {
+        return;
+    }
+    for (entity, tween, mut transform) in tweens_q.iter_mut() {
+        transform.translation = tween.to;
+        commands.entity(entity).remove::<Tween>();
+    }
+    for (entity, bump, mut transform) in bumps_q.iter_mut() {
+        transform.translation = bump.base;
+        commands.entity(entity).remove::<Bump>();
+    }
+    for (entity, flash, mut sprite) in flashes_q.iter_mut() {
+        sprite.color = flash.color.with_a(sprite.color.a());
+        commands.entity(entity).remove::<HitFlash>();
+    }
+    for entity in texts_q.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+// turns go on once moves and attacks have played, or after waiting max_block for them
+pub fn animations_settled(
+    settings: Res<AnimationSettings>,
+    tweens_q: Query<&Tween>,
+    bumps_q: Query<&Bump>,
+    flashes_q: Query<&HitFlash>,
+) -> bool {
+    tweens_q.iter().all(|a| a.elapsed >= settings.max_block)
+        && bumps_q.iter().all(|a| a.elapsed >= settings.max_block)
+        && flashes_q.iter().all(|a| a.elapsed >= settings.max_block)
+}
+
+pub struct TweenAnimationPlugin {
+    pub enabled: bool,
+}
+impl Plugin for TweenAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AnimationSettings {
+            enabled: self.enabled,
+            ..Default::default()
+        })
+        .add_event::<AttackEvent>();
+        // without animations, nothing needs to run. Headless runs have no fonts nor windows
+        if self.enabled {
+            app.add_systems(
+                Update,
+                (skip_animations, start_attack_animations, animate).chain(),
+            );
+        }
+    }
+}
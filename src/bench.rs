@@ -0,0 +1,115 @@
+use crate::prelude::*;
+use std::time::{Duration, Instant};
+
+// how many entities the benchmark scene spawns
+const BENCH_ENTITIES: usize = 20000;
+// how many of them move every frame
+const BENCH_MOVING: usize = 100;
+// frames to measure before printing the results and quitting
+const BENCH_FRAMES: u32 = 600;
+// frames to skip at the start, while everything gets loaded
+const BENCH_WARMUP: u32 = 60;
+
+#[derive(Resource, Default)]
+struct BenchStats {
+    frames: u32,
+    last: Option<Instant>,
+    total: Duration,
+}
+
+#[derive(Resource)]
+struct BenchSettings {
+    // marks every position changed each frame, like before transforms were change driven
+    touch_all: bool,
+}
+
+#[derive(Resource, Default)]
+struct BenchEntities(Vec<Entity>);
+
+fn spawn_bench_entities(
+    mut commands: Commands,
+    atlas: Res<CharsetAsset>,
+    mut entities: ResMut<BenchEntities>,
+) {
+    let mut rng = rand::thread_rng();
+    for _ in 0..BENCH_ENTITIES {
+        let entity = commands.spawn((
+            SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    color: Color::rgb(rng.gen(), rng.gen(), rng.gen()),
+                    index: rng.gen_range(1..255),
+                    custom_size: Some(Vec2::new(1.0, 1.0)),
+                    ..Default::default()
+                },
+                texture_atlas: atlas.atlas.clone(),
+                ..Default::default()
+            },
+            Position {
+                x: rng.gen_range(0..SCREEN_WIDTH),
+                y: rng.gen_range(0..SCREEN_HEIGHT),
+                z: 1,
+            },
+            TileSize::square(1.0),
+        ));
+        entities.0.push(entity.id());
+    }
+}
+
+// a few random entities take a step, the rest stay where they are
+fn move_bench_entities(
+    settings: Res<BenchSettings>,
+    entities: Res<BenchEntities>,
+    mut positions_q: Query<&mut Position>,
+) {
+    let mut rng = rand::thread_rng();
+    if settings.touch_all {
+        positions_q.iter_mut().for_each(|mut pos| pos.set_changed());
+    }
+    for _ in 0..BENCH_MOVING {
+        let entity = entities.0[rng.gen_range(0..entities.0.len())];
+        if let Ok(mut pos) = positions_q.get_mut(entity) {
+            pos.x = (pos.x + rng.gen_range(-1..=1)).clamp(0, SCREEN_WIDTH - 1);
+            pos.y = (pos.y + rng.gen_range(-1..=1)).clamp(0, SCREEN_HEIGHT - 1);
+        }
+    }
+}
+
+fn measure_frames(mut stats: ResMut<BenchStats>, mut exit: EventWriter<bevy::app::AppExit>) {
+    let now = Instant::now();
+    if let Some(last) = stats.last {
+        stats.frames += 1;
+        if stats.frames > BENCH_WARMUP {
+            stats.total += now - last;
+        }
+    }
+    stats.last = Some(now);
+
+    if stats.frames == BENCH_WARMUP + BENCH_FRAMES {
+        let average = stats.total / BENCH_FRAMES;
+        println!(
+            "{} entities, {} moving per frame: {:.3} ms per frame on average over {} frames",
+            BENCH_ENTITIES,
+            BENCH_MOVING,
+            average.as_secs_f64() * 1000.0,
+            BENCH_FRAMES
+        );
+        exit.send(bevy::app::AppExit);
+    }
+}
+
+// scene with thousands of sprites, few of them moving, to measure the cost of placing them.
+// Run with --bench-transforms, adding --touch-all to compare with updating every entity
+pub struct BenchPlugin {
+    pub touch_all: bool,
+}
+impl Plugin for BenchPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BenchSettings {
+            touch_all: self.touch_all,
+        })
+        .init_resource::<BenchStats>()
+        .init_resource::<BenchEntities>()
+        .add_systems(PostStartup, spawn_bench_entities)
+        .add_systems(Update, (move_bench_entities, measure_frames));
+    }
+}
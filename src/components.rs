@@ -3,16 +3,26 @@ use std::collections::HashSet;
 use crate::prelude::*;
 
 #[derive(Component)]
-pub struct Player {
-    pub map_level: u32,
-}
+pub struct Player;
 
 #[derive(Component)]
 pub struct Enemy;
 
+// unique enemy guarding a level
+#[derive(Component)]
+pub struct Boss;
+
 #[derive(Component)]
 pub struct MapTile;
 
+// a mark of the path the player would travel, drawn on revealed tiles out of view too
+#[derive(Component)]
+pub struct PathStep;
+
+// the cursor of the look mode, shown anywhere on the map like the path marks
+#[derive(Component)]
+pub struct LookCursor;
+
 #[derive(Component, Clone)]
 pub struct Naming(pub String);
 
@@ -46,6 +56,23 @@ impl TileSize {
 #[derive(Component)]
 pub struct ExitTile;
 
+#[derive(Component)]
+pub struct UpStairsTile;
+
+// stairs into a side branch of the dungeon
+#[derive(Component)]
+pub struct BranchStairsTile {
+    pub branch: usize,
+}
+
+// entity of a level the player left. It loses its position until the player comes back
+#[derive(Component)]
+pub struct Stashed {
+    pub level: LevelId,
+    pub position: Position,
+    pub visibility: Option<Visibility>,
+}
+
 #[derive(Component)]
 pub struct FieldOfView {
     pub visible_tiles: HashSet<Point>,
@@ -96,6 +123,14 @@ pub struct ProvidesDungeonMap;
 #[derive(Component)]
 pub struct Carried(pub Entity);
 
+// lights the tiles around, fading with distance. Carried lights shine from their carrier
+#[derive(Component, Clone, Copy)]
+pub struct LightSource {
+    pub radius: i32,
+    // light at the source, from 0 to 1
+    pub intensity: f32,
+}
+
 #[derive(Component)]
 pub struct ActivateItem {
     pub used_by: Entity,
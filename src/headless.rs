@@ -0,0 +1,372 @@
+use crate::prelude::*;
+use bevy::app::{AppExit, ScheduleRunnerPlugin};
+use bevy::input::InputPlugin;
+use bevy::input::InputSystem;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::fs;
+use std::time::Duration;
+
+// player turns played when no --turns is given
+const DEFAULT_TURNS: u32 = 1000;
+// percentage chance of the bot taking a random step instead of heading to its goal
+const BOT_WANDER_CHANCE: i32 = 15;
+
+// exit codes, so scripts can tell what happened
+const EXIT_OK: i32 = 0;
+const EXIT_INVARIANT: i32 = 1;
+const EXIT_BAD_ARGS: i32 = 2;
+
+// a direction of a script word, like "left" or "up-right"
+fn parse_direction(word: &str) -> Option<(i32, i32)> {
+    match word {
+        "left" => Some((-1, 0)),
+        "right" => Some((1, 0)),
+        "up" => Some((0, 1)),
+        "down" => Some((0, -1)),
+        "up-left" => Some((-1, 1)),
+        "up-right" => Some((1, 1)),
+        "down-left" => Some((-1, -1)),
+        "down-right" => Some((1, -1)),
+        _ => None,
+    }
+}
+
+// a script word as an action, like "left", "run:up", "travel:10,20", "grab" or "use:0"
+fn parse_action(word: &str) -> Option<PlayerAction> {
+    match word.split_once(':') {
+        Some(("use", item)) => item.parse().ok().map(PlayerAction::UseItem),
+        Some(("equip", item)) => item.parse().ok().map(PlayerAction::Equip),
+        Some(("travel", tile)) => {
+            let (x, y) = tile.split_once(',')?;
+            Some(PlayerAction::Travel(x.parse().ok()?, y.parse().ok()?))
+        }
+        Some(("run", direction)) => {
+            parse_direction(direction).map(|(dx, dy)| PlayerAction::Run(dx, dy))
+        }
+        Some(_) => None,
+        None => match word {
+            "wait" => Some(PlayerAction::Wait),
+            "grab" => Some(PlayerAction::Grab),
+            "explore" => Some(PlayerAction::Explore),
+            _ => parse_direction(word).map(|(dx, dy)| PlayerAction::Move(dx, dy)),
+        },
+    }
+}
+
+// who picks the player actions
+enum Driver {
+    // actions read from a file, the simulation stops when they run out
+    Script(Vec<PlayerAction>),
+    Bot,
+}
+
+#[derive(Resource)]
+struct Simulation {
+    driver: Driver,
+    seed: u64,
+    // the bot has its own generator, so the game one only sees what the game does
+    bot_rng: StdRng,
+    max_turns: u32,
+    turns: u32,
+    runs: u32,
+    deaths: u32,
+    victories: u32,
+    levels_entered: u32,
+}
+
+impl Simulation {
+    fn finish(&self, code: i32) -> ! {
+        println!(
+            "Seed {}: {} turns, {} runs, {} deaths, {} victories, {} levels entered",
+            self.seed, self.turns, self.runs, self.deaths, self.victories, self.levels_entered
+        );
+        std::process::exit(code);
+    }
+}
+
+// screens wait for a key press, the simulation goes on right away
+fn skip_screens(
+    state: Res<State<TurnState>>,
+    mut next_state: ResMut<NextState<TurnState>>,
+    mut sim: ResMut<Simulation>,
+) {
+    match state.get() {
+        TurnState::StartScreen => {
+            sim.runs += 1;
+            next_state.set(TurnState::AwaitingInput);
+        }
+        TurnState::NextLevel => {
+            sim.levels_entered += 1;
+            next_state.set(TurnState::AwaitingInput);
+        }
+        TurnState::GameOver => {
+            sim.deaths += 1;
+            next_state.set(TurnState::StartScreen);
+        }
+        TurnState::Victory => {
+            sim.victories += 1;
+            next_state.set(TurnState::StartScreen);
+        }
+        _ => (),
+    }
+}
+
+// sends the next action, resolve_actions plays it like a key press
+fn drive_player(
+    mut actions: EventWriter<PlayerAction>,
+    mut sim: ResMut<Simulation>,
+    mut exit: EventWriter<AppExit>,
+    next_state: Res<NextState<TurnState>>,
+    running: Res<Running>,
+    mb: Res<MapBuilder>,
+    player_q: Query<&Position, With<Player>>,
+    enemies_q: Query<&Position, With<Enemy>>,
+    items_q: Query<&Position, (With<Item>, Without<Carried>)>,
+    goals_q: Query<&Position, Or<(With<ExitTile>, With<AmuletOfYala>)>>,
+) {
+    // the last action was taken, the turn starts after this frame input. A run takes
+    // its own steps
+    if next_state.0.is_some() || running.is_running() {
+        return;
+    }
+    if sim.turns >= sim.max_turns {
+        exit.send(AppExit);
+        return;
+    }
+    let player_pos = match player_q.get_single() {
+        Ok(pos) => *pos,
+        Err(_) => return,
+    };
+
+    let turn = sim.turns as usize;
+    let action = match &sim.driver {
+        Driver::Script(actions) => match actions.get(turn) {
+            Some(action) => *action,
+            None => {
+                exit.send(AppExit);
+                return;
+            }
+        },
+        Driver::Bot => bot_action(
+            &mut sim.bot_rng,
+            &mb,
+            player_pos,
+            &enemies_q,
+            &items_q,
+            &goals_q,
+        ),
+    };
+
+    actions.send(action);
+    sim.turns += 1;
+}
+
+// attacks what is next to it, grabs what it stands on, and otherwise heads to the exit
+fn bot_action(
+    rng: &mut StdRng,
+    mb: &MapBuilder,
+    player_pos: Position,
+    enemies_q: &Query<&Position, With<Enemy>>,
+    items_q: &Query<&Position, (With<Item>, Without<Carried>)>,
+    goals_q: &Query<&Position, Or<(With<ExitTile>, With<AmuletOfYala>)>>,
+) -> PlayerAction {
+    let steps = [
+        (-1, 0),
+        (1, 0),
+        (0, -1),
+        (0, 1),
+        (-1, -1),
+        (1, -1),
+        (-1, 1),
+        (1, 1),
+    ];
+    let step_to = |(dx, dy): (i32, i32)| Position::new(player_pos.x + dx, player_pos.y + dy, 0);
+
+    if let Some(step) = steps.iter().find(|step| {
+        mb.map.can_step(player_pos, step_to(**step))
+            && enemies_q.iter().any(|pos| *pos == step_to(**step))
+    }) {
+        return PlayerAction::Move(step.0, step.1);
+    }
+    if items_q.iter().any(|pos| *pos == player_pos) {
+        return PlayerAction::Grab;
+    }
+
+    let goals: Vec<usize> = goals_q
+        .iter()
+        .filter_map(|pos| mb.map.try_idx(*pos))
+        .collect();
+    if goals.is_empty() || rng.gen_range(0..100) < BOT_WANDER_CHANCE {
+        let step = steps[rng.gen_range(0..steps.len())];
+        return PlayerAction::Move(step.0, step.1);
+    }
+
+    let dijkstra_map = DijkstraMap::new(SCREEN_WIDTH, SCREEN_HEIGHT, &goals, &mb.map, 1024.0);
+    steps
+        .iter()
+        .filter(|step| mb.map.can_step(player_pos, step_to(**step)))
+        .filter_map(|step| {
+            mb.map
+                .try_idx(step_to(*step))
+                .map(|idx| (*step, dijkstra_map.map[idx]))
+        })
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+        .map_or(PlayerAction::Wait, |(step, _)| {
+            PlayerAction::Move(step.0, step.1)
+        })
+}
+
+// checks the world is consistent between turns, and stops with an error when it is not
+fn check_invariants(
+    sim: Res<Simulation>,
+    mb: Res<MapBuilder>,
+    player_q: Query<&Position, With<Player>>,
+    actors_q: Query<(Entity, &Position, Option<&Naming>), Or<(With<Player>, With<Enemy>)>>,
+    positions_q: Query<&Position>,
+) {
+    let mut violations = Vec::new();
+
+    if let Ok(player_pos) = player_q.get_single() {
+        if !mb.map.can_enter_tile(*player_pos) {
+            violations.push(format!(
+                "player inside a wall at {},{}",
+                player_pos.x, player_pos.y
+            ));
+        }
+    }
+
+    // every actor is where the occupation map says
+    for (entity, pos, name) in actors_q.iter() {
+        let name = name.map_or("?", |name| name.0.as_str());
+        match mb.map.try_idx(*pos) {
+            Some(idx) if mb.map.occupation[idx] == Some(entity) => (),
+            Some(_) => violations.push(format!(
+                "{} at {},{} is not in the occupation map",
+                name, pos.x, pos.y
+            )),
+            None => violations.push(format!("{} out of the map", name)),
+        }
+    }
+
+    // and the occupation map only has actors where they are
+    for (idx, occupant) in mb.map.occupation.iter().enumerate() {
+        if let Some(entity) = occupant {
+            let in_place = positions_q
+                .get(*entity)
+                .map_or(false, |pos| mb.map.try_idx(*pos) == Some(idx));
+            if !in_place {
+                let pt = mb.map.index_to_point2d(idx);
+                violations.push(format!(
+                    "occupation map has {:?} at {},{} but it is not there",
+                    entity, pt.x, pt.y
+                ));
+            }
+        }
+    }
+
+    if !violations.is_empty() {
+        for violation in violations.iter() {
+            println!("Invariant violated at turn {}: {}", sim.turns, violation);
+        }
+        sim.finish(EXIT_INVARIANT);
+    }
+}
+
+fn report(sim: Res<Simulation>, mut exits: EventReader<AppExit>) {
+    if exits.read().next().is_some() {
+        sim.finish(EXIT_OK);
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Simulation, String> {
+    let value = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|i| args.get(i + 1))
+    };
+
+    let max_turns = match value("--turns") {
+        Some(turns) => turns
+            .parse()
+            .map_err(|_| format!("--turns needs a number, not {}", turns))?,
+        None => DEFAULT_TURNS,
+    };
+
+    let driver = match value("--script") {
+        Some(path) => {
+            let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            let actions = text
+                .split_whitespace()
+                .map(|word| parse_action(word).ok_or(format!("unknown action {}", word)))
+                .collect::<Result<Vec<_>, _>>()?;
+            Driver::Script(actions)
+        }
+        None => Driver::Bot,
+    };
+
+    let seed = seed_from_args(args);
+    Ok(Simulation {
+        driver,
+        seed,
+        bot_rng: StdRng::seed_from_u64(seed),
+        max_turns,
+        turns: 0,
+        runs: 0,
+        deaths: 0,
+        victories: 0,
+        levels_entered: 0,
+    })
+}
+
+// runs the game without window nor rendering. The player is played by a script of actions
+// (--script file, with words like left, up-right, run:down, explore, wait, grab, use:0...) or by
+// a simple bot, for --turns turns. --seed gives back the same run. Exits with 1 if the world
+// ever gets inconsistent
+pub fn run(args: &[String]) {
+    let sim = match parse_args(args) {
+        Ok(sim) => sim,
+        Err(e) => {
+            println!("Error: {}", e);
+            std::process::exit(EXIT_BAD_ARGS);
+        }
+    };
+
+    App::new()
+        .add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::ZERO)))
+        .add_plugins(InputPlugin)
+        .init_resource::<Assets<Mesh>>()
+        .init_resource::<Assets<ColorMaterial>>()
+        // nothing is drawn, the sprites don't need a real sprite sheet
+        .insert_resource(CharsetAsset {
+            atlas: Handle::default(),
+            texture: Handle::default(),
+        })
+        .insert_resource(Tilesets::load())
+        .insert_resource(GameLog::new())
+        .insert_resource(KeyBindings::load())
+        .insert_resource(sim)
+        .add_state::<TurnState>()
+        .add_state::<PopUpState>()
+        .add_plugins(MapPlugin {
+            rules: MovementRules::from_args(args),
+        })
+        .add_plugins(SpawnerPlugin)
+        .add_plugins(SystemsPlugin)
+        .add_plugins(TweenAnimationPlugin { enabled: false })
+        .add_systems(Update, skip_screens)
+        .add_systems(
+            PreUpdate,
+            drive_player
+                .after(InputSystem)
+                .run_if(in_state(TurnState::AwaitingInput)),
+        )
+        .add_systems(
+            Last,
+            (
+                check_invariants.run_if(in_state(TurnState::AwaitingInput)),
+                report,
+            ),
+        )
+        .run();
+}
@@ -0,0 +1,155 @@
+use crate::prelude::*;
+use ron::de::from_bytes;
+use serde::Deserialize;
+use std::fs;
+
+// the bindings the game ships with
+static DEFAULT_KEYBINDINGS: &[u8] = include_bytes!("../assets/keybindings.ron");
+// the player own bindings, next to the game, replace the default ones
+const USER_KEYBINDINGS_FILE: &str = "keybindings.ron";
+
+#[derive(Resource, Deserialize, Debug)]
+pub struct KeyBindings {
+    // the keys doing each action, in the order they are listed in the controls screen
+    pub actions: Vec<(PlayerAction, Vec<KeyCode>)>,
+    pub menu_up: Vec<KeyCode>,
+    pub menu_down: Vec<KeyCode>,
+    pub menu_select: Vec<KeyCode>,
+    pub menu_close: Vec<KeyCode>,
+    // held with a move key, the player runs in that direction
+    #[serde(default = "default_run_keys")]
+    pub run: Vec<KeyCode>,
+    // seconds a move key is held before it repeats, and then between the repeats
+    #[serde(default = "default_repeat_delay")]
+    pub repeat_delay: f32,
+    #[serde(default = "default_repeat_interval")]
+    pub repeat_interval: f32,
+}
+
+// bindings files older than running and key repeat get these
+fn default_run_keys() -> Vec<KeyCode> {
+    vec![KeyCode::ShiftLeft, KeyCode::ShiftRight]
+}
+
+fn default_repeat_delay() -> f32 {
+    0.3
+}
+
+fn default_repeat_interval() -> f32 {
+    0.1
+}
+
+impl KeyBindings {
+    pub fn load() -> Self {
+        let default = || {
+            from_bytes::<KeyBindings>(DEFAULT_KEYBINDINGS).expect("Unable to load key bindings.")
+        };
+        let bindings = match fs::read(USER_KEYBINDINGS_FILE) {
+            Ok(bytes) => from_bytes::<KeyBindings>(&bytes).unwrap_or_else(|e| {
+                println!(
+                    "Warning: unable to load {}, using the default keys: {}",
+                    USER_KEYBINDINGS_FILE, e
+                );
+                default()
+            }),
+            Err(_) => default(),
+        };
+        bindings.warn_duplicates();
+        bindings
+    }
+
+    // a key doing two actions only does the first one
+    fn warn_duplicates(&self) {
+        let mut seen = Vec::new();
+        for (_, keys) in self.actions.iter() {
+            for key in keys.iter() {
+                if seen.contains(key) {
+                    println!(
+                        "Warning: the key {:?} is bound to more than one action",
+                        key
+                    );
+                }
+                seen.push(*key);
+            }
+        }
+    }
+
+    pub fn action(&self, key: KeyCode) -> Option<PlayerAction> {
+        self.actions
+            .iter()
+            .find(|(_, keys)| keys.contains(&key))
+            .map(|(action, _)| *action)
+    }
+
+    pub fn keys(&self, action: PlayerAction) -> &[KeyCode] {
+        self.actions
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map_or(&[], |(_, keys)| keys.as_slice())
+    }
+
+    // the lines of the controls screen, as the name of what is done and its keys
+    pub fn describe(&self) -> Vec<(String, String)> {
+        let keys = |keys: &[KeyCode]| key_names(keys, ", ");
+        let mut lines: Vec<(String, String)> = self
+            .actions
+            .iter()
+            .map(|(action, bound)| (action_name(*action), keys(bound)))
+            .collect();
+        lines.push((
+            "Run".to_string(),
+            format!("{} + move", key_names(&self.run, " or ")),
+        ));
+        lines.push(("Travel".to_string(), "Left click".to_string()));
+        lines.push(("Menu up".to_string(), keys(&self.menu_up)));
+        lines.push(("Menu down".to_string(), keys(&self.menu_down)));
+        lines.push(("Menu select".to_string(), keys(&self.menu_select)));
+        lines.push(("Menu close".to_string(), keys(&self.menu_close)));
+        lines
+    }
+}
+
+// the keys as shown to the player, like "Left, Numpad4, H"
+pub fn key_names<'a>(keys: impl IntoIterator<Item = &'a KeyCode>, separator: &str) -> String {
+    keys.into_iter()
+        .map(|key| format!("{:?}", key))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn action_name(action: PlayerAction) -> String {
+    match action {
+        PlayerAction::Move(dx, dy) => {
+            let vertical = match dy {
+                1 => "up",
+                -1 => "down",
+                _ => "",
+            };
+            let horizontal = match dx {
+                -1 => "left",
+                1 => "right",
+                _ => "",
+            };
+            let separator = if vertical.is_empty() || horizontal.is_empty() {
+                ""
+            } else {
+                "-"
+            };
+            format!("Move {}{}{}", vertical, separator, horizontal)
+        }
+        PlayerAction::Run(dx, dy) => action_name(PlayerAction::Move(dx, dy)).replace("Move", "Run"),
+        PlayerAction::Explore => "Auto-explore".to_string(),
+        PlayerAction::Travel(..) => "Travel".to_string(),
+        PlayerAction::Wait => "Wait".to_string(),
+        PlayerAction::Grab => "Grab item".to_string(),
+        PlayerAction::UseItem(item) => format!("Use item {}", item + 1),
+        PlayerAction::Equip(item) => format!("Equip weapon {}", item + 1),
+        PlayerAction::OpenInventory => "Inventory".to_string(),
+        PlayerAction::OpenEquipment => "Equipment".to_string(),
+        PlayerAction::OpenMap => "Level map".to_string(),
+        PlayerAction::Look => "Look around".to_string(),
+        PlayerAction::CloseMenu => "Close menu".to_string(),
+        PlayerAction::CycleTileset => "Switch tileset".to_string(),
+        PlayerAction::Quit => "Quit".to_string(),
+    }
+}
@@ -1,15 +1,23 @@
 #![warn(clippy::pedantic)]
 
+mod animation;
+mod bench;
 mod components;
+mod headless;
+mod keybindings;
 mod map_builder;
 mod render_utils;
+mod replay;
 mod resources;
+mod rng;
 mod spawner;
 mod systems;
+mod tileset;
 mod ui;
 mod utils;
 
 mod prelude {
+    pub use crate::animation::*;
     pub use bevy::prelude::*;
     pub use bevy::window::PrimaryWindow;
     pub use bevy::winit::WinitSettings;
@@ -20,11 +28,15 @@ mod prelude {
     pub const SCREEN_HEIGHT: i32 = 80;
     pub const UI_HEIGHT: i32 = 10;
     pub use crate::components::*;
+    pub use crate::keybindings::*;
     pub use crate::map_builder::*;
     pub use crate::render_utils::*;
+    pub use crate::replay::*;
     pub use crate::resources::*;
+    pub use crate::rng::*;
     pub use crate::spawner::*;
     pub use crate::systems::*;
+    pub use crate::tileset::*;
     pub use crate::ui::*;
     pub use crate::utils::*;
     pub use rand::Rng;
@@ -35,17 +47,11 @@ use prelude::*;
 fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    tilesets: Res<Tilesets>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
 ) {
-    // Setup the sprite sheet
-    let texture_handle = asset_server.load("terminal8x8_transparent.png");
-    let texture_atlas =
-        TextureAtlas::from_grid(texture_handle, Vec2::new(8.0, 8.0), 16, 16, None, None);
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-    // add sprite atlas as resource
-    commands.insert_resource(CharsetAsset {
-        atlas: texture_atlas_handle.clone(),
-    });
+    // Setup the sprite sheet of the tileset in use, and add it as resource
+    commands.insert_resource(tilesets.charset(&asset_server, &mut texture_atlases));
 
     // Add a 2D Camera
     let mut cam = Camera2dBundle::default();
@@ -54,29 +60,52 @@ fn setup(
 }
 
 fn main() {
-    App::new()
-        .add_plugins(
-            DefaultPlugins
-                .set(ImagePlugin::default_nearest())
-                .set(WindowPlugin {
-                    primary_window: Some(Window {
-                        title: "Roguelike Game".to_string(),
-                        resolution: (SCREEN_WIDTH as f32 * 10.0, SCREEN_HEIGHT as f32 * 10.0)
-                            .into(),
-                        ..Default::default()
-                    }),
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|arg| arg == "--headless") {
+        headless::run(&args);
+        return;
+    }
+
+    let mut app = App::new();
+    app.add_plugins(
+        DefaultPlugins
+            .set(ImagePlugin::default_nearest())
+            .set(WindowPlugin {
+                primary_window: Some(Window {
+                    title: "Roguelike Game".to_string(),
+                    resolution: (SCREEN_WIDTH as f32 * 10.0, SCREEN_HEIGHT as f32 * 10.0).into(),
                     ..Default::default()
                 }),
-        )
-        .add_state::<TurnState>()
-        .add_state::<PopUpState>()
-        //.insert_resource(WinitSettings::desktop_app())
-        .insert_resource(ClearColor(Color::rgb(0.0, 0.0, 0.0)))
-        .add_systems(Startup, setup)
-        .add_plugins(MapPlugin)
-        .add_plugins(SpawnerPlugin)
-        .add_plugins(SystemsPlugin)
-        .add_plugins(UIPlugin)
-        .add_systems(PostUpdate, (position_translation, size_scaling))
-        .run();
+                ..Default::default()
+            }),
+    )
+    //.insert_resource(WinitSettings::desktop_app())
+    .insert_resource(ClearColor(Color::rgb(0.0, 0.0, 0.0)))
+    .insert_resource(Tilesets::load())
+    .add_systems(Startup, setup)
+    .add_systems(PostUpdate, (position_translation, size_scaling));
+
+    if args.iter().any(|arg| arg == "--bench-transforms") {
+        // only the benchmark scene, without the game
+        app.add_plugins(bench::BenchPlugin {
+            touch_all: args.iter().any(|arg| arg == "--touch-all"),
+        });
+    } else {
+        app.insert_resource(KeyBindings::load())
+            .add_state::<TurnState>()
+            .add_state::<PopUpState>()
+            .add_plugins(MapPlugin {
+                rules: MovementRules::from_args(&args),
+            })
+            .add_plugins(SpawnerPlugin)
+            .add_plugins(SystemsPlugin)
+            .add_plugins(UIPlugin)
+            .add_plugins(TilesetPlugin)
+            .add_plugins(TweenAnimationPlugin {
+                enabled: !args.iter().any(|arg| arg == "--no-animations"),
+            })
+            // after the map plugin, replays follow the movement rules they were recorded with
+            .add_plugins(ReplayPlugin::from_args(&args));
+    }
+    app.run();
 }
@@ -12,7 +12,12 @@ impl MapArchitect for CellularAutomataArchitect {
             player_start: Position::new(0, 0, 0),
             enemies_start: Vec::new(),
             amulet_start: Position::new(0, 0, 0),
-            theme: super::themes::ForestTheme::new(),
+            boss_start: None,
+            branch_stairs: Vec::new(),
+            monster_starts: Vec::new(),
+            item_starts: Vec::new(),
+            regions: RegionMap::default(),
+            theme: Theme::named("Forest"),
         };
 
         self.random_noise_map(&mut mb.map);
@@ -32,7 +37,7 @@ impl MapArchitect for CellularAutomataArchitect {
 
 impl CellularAutomataArchitect {
     fn random_noise_map(&mut self, map: &mut Map) {
-        let mut rng = rand::thread_rng();
+        let mut rng = game_rng();
 
         map.tiles.iter_mut().for_each(|t| {
             let roll = rng.gen_range(0..100);
@@ -0,0 +1,18 @@
+use crate::prelude::*;
+use std::collections::HashMap;
+
+// maps of the levels the player left, by level. Their entities are kept as Stashed
+#[derive(Resource, Default)]
+pub struct LevelCache {
+    pub levels: HashMap<LevelId, MapBuilder>,
+}
+
+// run condition, the level being entered needs its tiles and entities spawned
+pub fn level_is_new(transition: Res<LevelTransition>) -> bool {
+    !transition.restored
+}
+
+// run condition, the level being entered comes from the cache
+pub fn level_is_restored(transition: Res<LevelTransition>) -> bool {
+    transition.restored
+}
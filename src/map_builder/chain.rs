@@ -0,0 +1,162 @@
+use super::MapArchitect;
+use crate::prelude::*;
+
+// percentage chance of a wall next to floor being worn down on each erode pass
+const ERODE_CHANCE: i32 = 30;
+
+// changes a map already built by an architect
+pub trait MetaBuilder {
+    fn apply(&mut self, mb: &mut MapBuilder);
+}
+
+// an architect followed by the modifiers that refine its map, run in order
+pub(super) struct BuilderChain {
+    architect: Box<dyn MapArchitect>,
+    modifiers: Vec<Box<dyn MetaBuilder>>,
+}
+
+impl BuilderChain {
+    pub(super) fn new(architect: Box<dyn MapArchitect>) -> Self {
+        Self {
+            architect,
+            modifiers: Vec::new(),
+        }
+    }
+
+    pub(super) fn with(mut self, modifier: Box<dyn MetaBuilder>) -> Self {
+        self.modifiers.push(modifier);
+        self
+    }
+
+    pub(super) fn build(&mut self) -> MapBuilder {
+        let mut mb = self.architect.new();
+        mb.repair_connectivity(self.architect.repair());
+        for modifier in self.modifiers.iter_mut() {
+            modifier.apply(&mut mb);
+        }
+        mb
+    }
+}
+
+// the meta builder for a modifier of the level plan
+pub(super) fn meta_builder(modifier: &MapModifier, vaults: &Vaults) -> Box<dyn MetaBuilder> {
+    match modifier {
+        MapModifier::Erode(iterations) => Box::new(Erode {
+            iterations: *iterations,
+        }),
+        MapModifier::AddRooms(count) => Box::new(AddRooms { count: *count }),
+        MapModifier::CullUnreachable => Box::new(CullUnreachable {}),
+        MapModifier::TunnelUnreachable => Box::new(TunnelUnreachable {}),
+        MapModifier::Vault(name) => {
+            let vault = match name {
+                Some(name) => vaults.get(name),
+                None => vaults.random(),
+            };
+            if vault.is_none() {
+                println!("Warning: no vault {:?} to stamp", name);
+            }
+            Box::new(StampVault {
+                vault: vault.cloned(),
+            })
+        }
+        MapModifier::PlaceExit => Box::new(PlaceExit {}),
+        MapModifier::PlaceSpawns => Box::new(PlaceSpawns {}),
+        MapModifier::Theme(name) => Box::new(ApplyTheme { name: name.clone() }),
+    }
+}
+
+pub struct Erode {
+    pub iterations: usize,
+}
+
+impl MetaBuilder for Erode {
+    fn apply(&mut self, mb: &mut MapBuilder) {
+        let mut rng = game_rng();
+        for _ in 0..self.iterations {
+            let mut new_tiles = mb.map.tiles.clone();
+            for y in 1..SCREEN_HEIGHT - 1 {
+                for x in 1..SCREEN_WIDTH - 1 {
+                    let idx = map_idx(x, y);
+                    let next_to_floor = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                        .iter()
+                        .any(|(dx, dy)| mb.map.tiles[map_idx(x + dx, y + dy)] == TileType::Floor);
+                    if mb.map.tiles[idx] == TileType::Wall
+                        && next_to_floor
+                        && rng.gen_range(0..100) < ERODE_CHANCE
+                    {
+                        new_tiles[idx] = TileType::Floor;
+                    }
+                }
+            }
+            mb.map.tiles = new_tiles;
+        }
+        mb.wall_off_void();
+        mb.clean_walls_replace_with_void();
+    }
+}
+
+pub struct AddRooms {
+    pub count: usize,
+}
+
+impl MetaBuilder for AddRooms {
+    fn apply(&mut self, mb: &mut MapBuilder) {
+        mb.build_random_rooms(self.count);
+        mb.tunnel_unreachable();
+        mb.clean_walls_replace_with_void();
+    }
+}
+
+pub struct CullUnreachable {}
+
+impl MetaBuilder for CullUnreachable {
+    fn apply(&mut self, mb: &mut MapBuilder) {
+        mb.cull_unreachable();
+    }
+}
+
+pub struct TunnelUnreachable {}
+
+impl MetaBuilder for TunnelUnreachable {
+    fn apply(&mut self, mb: &mut MapBuilder) {
+        mb.tunnel_unreachable();
+    }
+}
+
+pub struct StampVault {
+    pub vault: Option<Vault>,
+}
+
+impl MetaBuilder for StampVault {
+    fn apply(&mut self, mb: &mut MapBuilder) {
+        if let Some(vault) = &self.vault {
+            mb.apply_vault(vault);
+        }
+    }
+}
+
+pub struct PlaceExit {}
+
+impl MetaBuilder for PlaceExit {
+    fn apply(&mut self, mb: &mut MapBuilder) {
+        mb.amulet_start = mb.find_most_distant();
+    }
+}
+
+pub struct PlaceSpawns {}
+
+impl MetaBuilder for PlaceSpawns {
+    fn apply(&mut self, mb: &mut MapBuilder) {
+        mb.enemies_start = mb.spawn_monsters(&mb.player_start);
+    }
+}
+
+pub struct ApplyTheme {
+    pub name: String,
+}
+
+impl MetaBuilder for ApplyTheme {
+    fn apply(&mut self, mb: &mut MapBuilder) {
+        mb.theme = Theme::named(&self.name);
+    }
+}
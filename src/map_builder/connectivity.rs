@@ -0,0 +1,147 @@
+use crate::prelude::*;
+use std::collections::VecDeque;
+
+// what to do with walkable tiles that can't be reached from the player start
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ConnectivityRepair {
+    // turn them into walls
+    Cull,
+    // dig tunnels joining them to the rest of the map
+    Tunnel,
+}
+
+impl MapBuilder {
+    // flood fill from the player start, true for every tile the player can walk to
+    pub fn reachable_tiles(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.map.tiles.len()];
+        let start = self.map.point2d_to_index(self.player_start.into());
+        if !self.map.can_enter_tile(self.player_start) {
+            return reachable;
+        }
+
+        let mut open = VecDeque::from([start]);
+        reachable[start] = true;
+        while let Some(idx) = open.pop_front() {
+            for (exit, _) in self.map.get_available_exits(idx) {
+                if !reachable[exit] {
+                    reachable[exit] = true;
+                    open.push_back(exit);
+                }
+            }
+        }
+        reachable
+    }
+
+    pub fn repair_connectivity(&mut self, repair: ConnectivityRepair) {
+        match repair {
+            ConnectivityRepair::Cull => self.cull_unreachable(),
+            ConnectivityRepair::Tunnel => self.tunnel_unreachable(),
+        }
+    }
+
+    // walls over every walkable tile not connected to the player start
+    pub fn cull_unreachable(&mut self) {
+        let reachable = self.reachable_tiles();
+        let mut culled = false;
+        for (idx, tile) in self.map.tiles.iter_mut().enumerate() {
+            if !reachable[idx] && matches!(tile, TileType::Floor | TileType::Door) {
+                *tile = TileType::Wall;
+                culled = true;
+            }
+        }
+        if culled {
+            self.enemies_start
+                .retain(|pos| reachable[map_idx(pos.x, pos.y)]);
+            self.clean_walls_replace_with_void();
+        }
+    }
+
+    // joins every disconnected area to the closest reachable tile with a corridor
+    pub fn tunnel_unreachable(&mut self) {
+        loop {
+            let reachable = self.reachable_tiles();
+            let unreachable: Vec<usize> = (0..self.map.tiles.len())
+                .filter(|idx| !reachable[*idx] && self.map.can_enter_tile(self.idx_to_pos(*idx)))
+                .collect();
+            if unreachable.is_empty() {
+                return;
+            }
+
+            // breadth first search through anything, starting from all the unreachable tiles,
+            // until it bumps into the reachable part. Remember where each path started
+            let mut origin: Vec<Option<usize>> = vec![None; self.map.tiles.len()];
+            let mut open = VecDeque::new();
+            for idx in unreachable {
+                origin[idx] = Some(idx);
+                open.push_back(idx);
+            }
+
+            let mut tunnel = None;
+            while let Some(idx) = open.pop_front() {
+                if reachable[idx] {
+                    tunnel = origin[idx].map(|from| (from, idx));
+                    break;
+                }
+                let pos = self.idx_to_pos(idx);
+                for delta in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                    let next = Position::new_from2d(pos.x + delta.0, pos.y + delta.1);
+                    // keep the outer boundary as it is
+                    if next.x < 1
+                        || next.y < 1
+                        || next.x >= SCREEN_WIDTH - 1
+                        || next.y >= SCREEN_HEIGHT - 1
+                    {
+                        continue;
+                    }
+                    let next_idx = map_idx(next.x, next.y);
+                    if origin[next_idx].is_none() {
+                        origin[next_idx] = origin[idx];
+                        open.push_back(next_idx);
+                    }
+                }
+            }
+
+            match tunnel {
+                Some((from, to)) => {
+                    let (from, to) = (self.idx_to_pos(from), self.idx_to_pos(to));
+                    self.apply_horizontal_tunnel_walls(from.x, to.x, from.y);
+                    self.apply_vertical_tunnel_walls(from.y, to.y, to.x);
+                }
+                // nothing reachable at all, the map is beyond repair
+                None => return,
+            }
+        }
+    }
+
+    // a map is valid if the player can walk to the stairs, the amulet, the boss and every spawn
+    pub fn is_valid(&self) -> bool {
+        let reachable = self.reachable_tiles();
+        let is_reachable =
+            |pos: &Position| self.map.try_idx(*pos).map_or(false, |idx| reachable[idx]);
+
+        let exits_reachable = self
+            .map
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| {
+                matches!(
+                    t,
+                    TileType::Exit | TileType::UpStairs | TileType::BranchStairs
+                )
+            })
+            .all(|(idx, _)| reachable[idx]);
+
+        exits_reachable
+            && is_reachable(&self.player_start)
+            && is_reachable(&self.amulet_start)
+            && self.boss_start.as_ref().map_or(true, is_reachable)
+            && self.enemies_start.iter().all(is_reachable)
+            && self.monster_starts.iter().all(is_reachable)
+            && self.item_starts.iter().all(is_reachable)
+    }
+
+    fn idx_to_pos(&self, idx: usize) -> Position {
+        self.map.index_to_point2d(idx).into()
+    }
+}
@@ -10,7 +10,7 @@ pub trait DrunkardWalk {
     fn drunkard(&mut self, start: &Position, map: &mut Map) {
         let mut drunkard_pos = start.clone();
         let mut distance_staggered = 0;
-        let mut rng = rand::thread_rng();
+        let mut rng = game_rng();
 
         loop {
             let drunk_idx = map.point2d_to_index(drunkard_pos.into());
@@ -34,7 +34,7 @@ pub trait DrunkardWalk {
     }
 
     fn drunkard_loop(&mut self, center: &Position, map: &mut Map, floor_vs_wall_ratio: f32) {
-        let mut rng = rand::thread_rng();
+        let mut rng = game_rng();
         let desired_floor = NUM_TILES as f32 / floor_vs_wall_ratio;
 
         while map.tiles.iter().filter(|t| **t == TileType::Floor).count() < desired_floor as usize {
@@ -73,7 +73,12 @@ impl MapArchitect for DrunkardsWalkArchitect {
             player_start: Position::new(0, 0, 0),
             enemies_start: Vec::new(),
             amulet_start: Position::new(0, 0, 0),
-            theme: super::themes::CaveTheme::new(),
+            boss_start: None,
+            branch_stairs: Vec::new(),
+            monster_starts: Vec::new(),
+            item_starts: Vec::new(),
+            regions: RegionMap::default(),
+            theme: Theme::named("Cave"),
         };
 
         mb.fill(TileType::Wall);
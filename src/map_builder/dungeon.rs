@@ -0,0 +1,92 @@
+use crate::prelude::*;
+
+// the main dungeon is the first branch, the side branches follow in the level plans order
+pub const MAIN_BRANCH: usize = 0;
+
+// a level of the dungeon, by branch and depth inside that branch
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct LevelId {
+    pub branch: usize,
+    pub depth: u32,
+}
+
+impl LevelId {
+    pub fn new(branch: usize, depth: u32) -> Self {
+        Self { branch, depth }
+    }
+}
+
+struct BranchNode {
+    name: String,
+    // level of the main dungeon with the stairs into the branch
+    entrance: Option<LevelId>,
+    // how many levels it has. The main dungeon never ends, it reuses its last plan
+    length: Option<u32>,
+}
+
+// how the levels connect to each other, and where the player is
+#[derive(Resource)]
+pub struct DungeonGraph {
+    branches: Vec<BranchNode>,
+    pub current: LevelId,
+}
+
+impl DungeonGraph {
+    pub fn new(plans: &LevelPlans) -> Self {
+        let mut branches = vec![BranchNode {
+            name: "Dungeon".to_string(),
+            entrance: None,
+            length: None,
+        }];
+        for branch in plans.branches.iter() {
+            branches.push(BranchNode {
+                name: branch.name.clone(),
+                entrance: Some(LevelId::new(MAIN_BRANCH, branch.entrance)),
+                length: Some(branch.levels.len() as u32),
+            });
+        }
+
+        Self {
+            branches,
+            current: LevelId::default(),
+        }
+    }
+
+    // level reached taking the stairs down, None at the bottom of a branch
+    pub fn down(&self, level: LevelId) -> Option<LevelId> {
+        let next = LevelId::new(level.branch, level.depth + 1);
+        match self.branches[level.branch].length {
+            Some(length) if next.depth >= length => None,
+            _ => Some(next),
+        }
+    }
+
+    // level reached taking the stairs up, the first level of a branch goes back to its entrance
+    pub fn up(&self, level: LevelId) -> Option<LevelId> {
+        if level.depth > 0 {
+            Some(LevelId::new(level.branch, level.depth - 1))
+        } else {
+            self.branches[level.branch].entrance
+        }
+    }
+
+    // side branches with their stairs in this level
+    pub fn branches_from(&self, level: LevelId) -> Vec<usize> {
+        (0..self.branches.len())
+            .filter(|branch| self.branches[*branch].entrance == Some(level))
+            .collect()
+    }
+
+    pub fn name(&self, branch: usize) -> &str {
+        &self.branches[branch].name
+    }
+
+    // how deep the level really is, used to pick what spawns there. Side branches continue
+    // from the depth of their entrance
+    pub fn difficulty(&self, level: LevelId) -> u32 {
+        match self.branches[level.branch].entrance {
+            Some(entrance) => self.difficulty(entrance) + 1 + level.depth,
+            None => level.depth,
+        }
+    }
+}
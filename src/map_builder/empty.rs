@@ -16,7 +16,7 @@ impl MapArchitect for EmptyArchitect {
         mb.fill(TileType::Floor);
         mb.player_start = Position::new_from2d(SCREEN_WIDTH/2, SCREEN_HEIGHT/2);
         mb.amulet_start = mb.find_most_distant();
-        let mut rng = rand::thread_rng();
+        let mut rng = game_rng();
         for _ in 0..50 {
             mb.enemies_start.push(
                 Position::new_from2d(
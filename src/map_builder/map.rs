@@ -1,12 +1,18 @@
 use crate::prelude::*;
+use serde::Deserialize;
 
 const NUM_TILES: usize = (SCREEN_WIDTH * SCREEN_HEIGHT) as usize;
+// cost of a diagonal step when finding paths
+const DIAGONAL_COST: f32 = 1.41;
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
 pub enum TileType {
     Wall,
     Floor,
     Exit,
+    UpStairs,
+    BranchStairs,
+    Door,
     Void,
 }
 
@@ -15,6 +21,12 @@ pub struct Map {
     pub tiles: Vec<TileType>,
     // entities occupying the tiles, like player, enemies, objects, ...
     pub occupation: Vec<Option<Entity>>,
+    // tiles the player has seen, or learned about with a magic map
+    pub revealed: Vec<bool>,
+    // if diagonal steps can go around the corner of a wall
+    pub cut_corners: bool,
+    // a roll from 0 to 99 per tile, picking the decoration the theme draws there
+    pub decorations: Vec<i32>,
 }
 
 impl Map {
@@ -22,6 +34,11 @@ impl Map {
         Self {
             tiles: vec![TileType::Void; NUM_TILES],
             occupation: vec![None; NUM_TILES],
+            revealed: vec![false; NUM_TILES],
+            // maps are generated without cutting corners, so what they connect is
+            // connected with both rules
+            cut_corners: false,
+            decorations: vec![0; NUM_TILES],
         }
     }
 
@@ -37,8 +54,14 @@ impl Map {
     pub fn can_enter_tile<T: Into<Position>>(&self, position: T) -> bool {
         let position = position.into();
         self.in_bounds(position)
-            && (self.tiles[map_idx(position.x, position.y)] == TileType::Floor
-                || self.tiles[map_idx(position.x, position.y)] == TileType::Exit)
+            && matches!(
+                self.tiles[map_idx(position.x, position.y)],
+                TileType::Floor
+                    | TileType::Exit
+                    | TileType::UpStairs
+                    | TileType::BranchStairs
+                    | TileType::Door
+            )
     }
 
     // checks if another entity like an enemy or player, are already in that cell
@@ -55,10 +78,23 @@ impl Map {
         }
     }
 
+    // a step to a neighbor tile, diagonal ones only around walls when cutting corners
+    pub fn can_step(&self, from: Position, to: Position) -> bool {
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        if dx.abs() > 1 || dy.abs() > 1 || !self.can_enter_tile(to) {
+            return false;
+        }
+        self.cut_corners
+            || dx == 0
+            || dy == 0
+            || (self.can_enter_tile(Position::new_from2d(from.x + dx, from.y))
+                && self.can_enter_tile(Position::new_from2d(from.x, from.y + dy)))
+    }
+
     fn valid_exit(&self, loc: Point, delta: Point) -> Option<usize> {
         let destination = loc + delta;
         if self.in_bounds(destination) {
-            if self.can_enter_tile(destination) {
+            if self.can_step(loc.into(), destination.into()) {
                 let idx = self.point2d_to_index(destination);
                 Some(idx)
             } else {
@@ -96,6 +132,17 @@ impl BaseMap for Map {
         if let Some(idx) = self.valid_exit(location, Point::new(0, 1)) {
             exits.push((idx, 1.0))
         }
+        // and the diagonals
+        for delta in [
+            Point::new(-1, -1),
+            Point::new(1, -1),
+            Point::new(-1, 1),
+            Point::new(1, 1),
+        ] {
+            if let Some(idx) = self.valid_exit(location, delta) {
+                exits.push((idx, DIAGONAL_COST))
+            }
+        }
         exits
     }
 
@@ -111,73 +158,3 @@ impl BaseMap for Map {
 pub fn map_idx(x: i32, y: i32) -> usize {
     ((y * SCREEN_WIDTH) + x) as usize
 }
-
-pub fn spawn_map_tiles(mut commands: Commands, mb: Res<MapBuilder>, atlas: Res<CharsetAsset>) {
-    for y in 0..SCREEN_HEIGHT {
-        for x in 0..SCREEN_WIDTH {
-            let idx = map_idx(x, y);
-            let glyph = mb.theme.tile_to_render(mb.map.tiles[idx]);
-
-            if let Some(glyph) = glyph {
-                match mb.map.tiles[idx] {
-                    TileType::Floor => {
-                        commands
-                            .spawn(SpriteBundle {
-                                sprite: Sprite {
-                                    color: glyph.color,
-                                    custom_size: Some(Vec2::new(1.0, 1.0)),
-                                    ..Default::default()
-                                },
-                                visibility: Visibility::Hidden,
-                                ..Default::default()
-                            })
-                            .insert(MapTile)
-                            .insert(Position { x: x, y: y, z: 1 })
-                            .insert(TileSize::square(1.0));
-                    }
-
-                    tile_type @ (TileType::Wall | TileType::Exit) => {
-                        // if exit, add entity with exit component, so it's easy to find later
-                        if tile_type == TileType::Exit {
-                            commands.spawn((Position { x, y, z: 1 }, ExitTile));
-                        }
-                        if let Some(bkg_color) = glyph.bkg_color {
-                            commands.spawn((
-                                MapTile,
-                                TileSize::square(1.0),
-                                Position { x, y, z: 0 },
-                                SpriteBundle {
-                                    sprite: Sprite {
-                                        color: bkg_color,
-                                        custom_size: Some(Vec2::new(1.0, 1.0)),
-                                        ..Default::default()
-                                    },
-                                    visibility: Visibility::Hidden,
-                                    ..Default::default()
-                                },
-                            ));
-                        }
-
-                        commands.spawn((
-                            MapTile,
-                            TileSize::square(1.0),
-                            Position { x, y, z: 1 },
-                            SpriteSheetBundle {
-                                texture_atlas: atlas.atlas.clone(),
-                                sprite: TextureAtlasSprite {
-                                    color: glyph.color,
-                                    custom_size: Some(Vec2::new(1.0, 1.0)),
-                                    index: glyph.index,
-                                    ..Default::default()
-                                },
-                                visibility: Visibility::Hidden,
-                                ..Default::default()
-                            },
-                        ));
-                    }
-                    TileType::Void => (),
-                }
-            }
-        }
-    }
-}
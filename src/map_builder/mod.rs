@@ -1,8 +1,12 @@
 use crate::prelude::*;
 use bracket_geometry::prelude::Rect;
+use rand::seq::SliceRandom;
+use std::collections::HashSet;
 
 mod map;
 pub use map::*;
+mod tilemap;
+pub use tilemap::*;
 
 mod rooms;
 use rooms::RoomsArchitect;
@@ -14,16 +18,52 @@ mod prefab;
 use prefab::PrefabArchitect;
 mod themes;
 pub use themes::*;
+mod plan;
+pub use plan::*;
+mod vaults;
+pub use vaults::*;
+mod connectivity;
+pub use connectivity::*;
+mod wfc;
+use wfc::WaveFunctionCollapseArchitect;
+mod chain;
+use chain::BuilderChain;
+mod region;
+pub use region::*;
+mod voronoi;
+use voronoi::VoronoiArchitect;
+mod cache;
+pub use cache::*;
+mod dungeon;
+pub use dungeon::*;
 
 trait MapArchitect {
     fn new(&mut self) -> MapBuilder;
+
+    // how to fix floor that ended up disconnected from the player start
+    fn repair(&self) -> ConnectivityRepair {
+        ConnectivityRepair::Cull
+    }
 }
 
 pub trait MapTheme: Sync + Send {
-    fn tile_to_render(&self, tile_type: TileType) -> Option<Glyph>;
+    // the decoration roll of the tile picks one of the theme variants, if any
+    fn tile_to_render(&self, tile_type: TileType, decoration: i32) -> Option<Glyph>;
+
+    // light reaching every tile of the level, from 0 (pitch black) to 1
+    fn ambient_light(&self) -> f32;
+
+    // how many torches to hang on the walls of the level
+    fn torches(&self) -> usize;
 }
 
 const NUM_ROOMS: usize = 5;
+// torches closer than this light the same spot
+const MIN_TORCH_DISTANCE: f32 = 10.0;
+// how many times to regenerate a level that fails validation
+const MAX_MAP_ATTEMPTS: usize = 20;
+// the vault used by the prefab architect
+const FORTRESS: &str = "Fortress";
 
 #[derive(Resource)]
 pub struct MapBuilder {
@@ -33,19 +73,56 @@ pub struct MapBuilder {
     pub player_start: Position,
     pub enemies_start: Vec<Position>,
     pub amulet_start: Position,
+    // where the level boss goes, marked by prefabs
+    pub boss_start: Option<Position>,
+    // stairs into side branches, with the branch they lead to
+    pub branch_stairs: Vec<(Position, usize)>,
+    // entities that must spawn at these spots, placed by vault markers
+    pub monster_starts: Vec<Position>,
+    pub item_starts: Vec<Position>,
+    // which area every tile belongs to
+    pub regions: RegionMap,
     pub theme: Box<dyn MapTheme>,
 }
 
 impl MapBuilder {
-    pub fn new() -> Self {
-        let mut rng = rand::thread_rng();
-        let mut architect: Box<dyn MapArchitect> = match rng.gen_range(0..1) {
-            0 => Box::new(RoomsArchitect {}),
-            1 => Box::new(DrunkardsWalkArchitect {}),
-            2 => Box::new(CellularAutomataArchitect {}),
-            _ => Box::new(PrefabArchitect {}),
-        };
-        architect.new()
+    // runs the architect of the plan and then its modifiers
+    pub fn from_plan(plan: &LevelPlan, vaults: &Vaults) -> Self {
+        let mut chain = BuilderChain::new(Self::architect(plan.architect, vaults));
+        for modifier in plan.modifiers.iter() {
+            chain = chain.with(chain::meta_builder(modifier, vaults));
+        }
+        chain.build()
+    }
+
+    fn architect(kind: ArchitectKind, vaults: &Vaults) -> Box<dyn MapArchitect> {
+        match kind {
+            ArchitectKind::Random => {
+                let mut rng = game_rng();
+                let kind = match rng.gen_range(0..1) {
+                    0 => ArchitectKind::Rooms,
+                    1 => ArchitectKind::Drunkard,
+                    2 => ArchitectKind::Automata,
+                    3 => ArchitectKind::WaveFunctionCollapse,
+                    4 => ArchitectKind::Voronoi,
+                    _ => ArchitectKind::Prefab,
+                };
+                Self::architect(kind, vaults)
+            }
+            ArchitectKind::Rooms => Box::new(RoomsArchitect {}),
+            ArchitectKind::Drunkard => Box::new(DrunkardsWalkArchitect {}),
+            ArchitectKind::Automata => Box::new(CellularAutomataArchitect {}),
+            ArchitectKind::Prefab => Box::new(PrefabArchitect {
+                vault: vaults
+                    .get(FORTRESS)
+                    .cloned()
+                    .expect("There is no Fortress vault for the prefab architect."),
+            }),
+            ArchitectKind::WaveFunctionCollapse => Box::new(WaveFunctionCollapseArchitect::new(
+                vaults.random_sample().cloned(),
+            )),
+            ArchitectKind::Voronoi => Box::new(VoronoiArchitect {}),
+        }
     }
 
     fn find_most_distant(&self) -> Position {
@@ -75,14 +152,94 @@ impl MapBuilder {
             .into()
     }
 
+    // floor tile as far as possible from both the player start and the exit
+    fn find_branch_stairs(&self) -> Position {
+        let from = |pos: Position| {
+            DijkstraMap::new(
+                SCREEN_WIDTH,
+                SCREEN_HEIGHT,
+                &[self.map.point2d_to_index(pos.into())],
+                &self.map,
+                1024.0,
+            )
+        };
+        let (from_player, from_exit) = (from(self.player_start), from(self.amulet_start));
+
+        let best = self
+            .map
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(idx, t)| **t == TileType::Floor && from_player.map[*idx] < f32::MAX)
+            .map(|(idx, _)| (idx, f32::min(from_player.map[idx], from_exit.map[idx])))
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map_or(0, |(idx, _)| idx);
+        self.map.index_to_point2d(best).into()
+    }
+
+    // walls next to floor where to hang the theme torches, kept apart from each other
+    pub fn torch_positions(&self) -> Vec<Position> {
+        let mut rng = game_rng();
+        let mut candidates: Vec<Point> = (0..self.map.tiles.len())
+            .filter(|idx| self.map.tiles[*idx] == TileType::Wall)
+            .map(|idx| self.map.index_to_point2d(idx))
+            .filter(|pt| {
+                [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                    .iter()
+                    .any(|(dx, dy)| self.map.can_enter_tile(*pt + Point::new(*dx, *dy)))
+            })
+            .collect();
+        candidates.shuffle(&mut rng);
+
+        let mut torches: Vec<Point> = Vec::new();
+        for pt in candidates {
+            if torches.len() >= self.theme.torches() {
+                break;
+            }
+            if torches
+                .iter()
+                .all(|t| DistanceAlg::Pythagoras.distance2d(*t, pt) >= MIN_TORCH_DISTANCE)
+            {
+                torches.push(pt);
+            }
+        }
+        torches.into_iter().map(|pt| (pt, 1).into()).collect()
+    }
+
+    // the floor tile closest to a given position
+    fn closest_floor(&self, target: Position) -> Position {
+        let closest_point = self
+            .map
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| **t == TileType::Floor)
+            .map(|(idx, _)| {
+                (
+                    idx,
+                    DistanceAlg::Pythagoras
+                        .distance2d(target.into(), self.map.index_to_point2d(idx)),
+                )
+            })
+            .min_by(|(_, distance), (_, distance2)| distance.partial_cmp(distance2).unwrap())
+            .map_or(0, |(idx, _)| idx);
+        self.map.index_to_point2d(closest_point).into()
+    }
+
     fn fill(&mut self, tile: TileType) {
         self.map.tiles.iter_mut().for_each(|t| *t = tile);
     }
 
-    fn build_random_rooms(&mut self) {
-        let mut rng = rand::thread_rng();
+    // adds up to count rooms that don't overlap the ones already there
+    fn build_random_rooms(&mut self, count: usize) {
+        const MAX_TRIES_PER_ROOM: usize = 100;
+        let mut rng = game_rng();
+        let target = self.rooms.len() + count;
 
-        while self.rooms.len() < NUM_ROOMS {
+        for _ in 0..count * MAX_TRIES_PER_ROOM {
+            if self.rooms.len() >= target {
+                break;
+            }
             let room = Rect::with_size(
                 rng.gen_range(2..SCREEN_WIDTH - 12),
                 rng.gen_range(2..SCREEN_HEIGHT - 12),
@@ -158,7 +315,7 @@ impl MapBuilder {
     }
 
     fn build_corridors(&mut self) {
-        let mut rng = rand::thread_rng();
+        let mut rng = game_rng();
         let mut rooms = self.rooms.clone();
         rooms.sort_by(|a, b| a.center().x.cmp(&b.center().x));
 
@@ -196,9 +353,10 @@ impl MapBuilder {
         self.map.occupation[new_idx] = Some(entity);
     }
 
+    // candidate spawn points. How many of them get used depends on the level spawn budget
     fn spawn_monsters(&self, start: &Position) -> Vec<Position> {
-        const NUM_MONSTERS: usize = 50;
-        let mut rng = rand::thread_rng();
+        const MAX_SPAWN_POINTS: usize = 200;
+        let mut rng = game_rng();
 
         let mut spawnable_tiles: Vec<Position> = self
             .map
@@ -216,7 +374,7 @@ impl MapBuilder {
             .collect();
 
         let mut spawns = Vec::new();
-        for _ in 0..NUM_MONSTERS {
+        for _ in 0..usize::min(MAX_SPAWN_POINTS, spawnable_tiles.len()) {
             let target_index = rng.gen_range(0..spawnable_tiles.len());
             spawns.push(spawnable_tiles[target_index].clone());
             spawnable_tiles.remove(target_index);
@@ -224,6 +382,54 @@ impl MapBuilder {
         spawns
     }
 
+    // positions to place a group of entities around an anchor point. If the anchor is inside
+    // a region the whole group stays in it, otherwise it uses the floor nearby
+    pub fn group_positions(
+        &self,
+        anchor: Position,
+        count: usize,
+        taken: &HashSet<usize>,
+    ) -> Vec<Position> {
+        const GROUP_RADIUS: f32 = 3.0;
+        let anchor_pt: Point = anchor.into();
+
+        let mut candidates: Vec<Point> = match self.regions.region_at(anchor) {
+            Some(region) => self
+                .regions
+                .positions(region)
+                .into_iter()
+                .map(Point::from)
+                .collect(),
+            None => self
+                .map
+                .tiles
+                .iter()
+                .enumerate()
+                .map(|(idx, _)| self.map.index_to_point2d(idx))
+                .filter(|pt| DistanceAlg::Pythagoras.distance2d(anchor_pt, *pt) <= GROUP_RADIUS)
+                .collect(),
+        };
+
+        candidates.retain(|pt| {
+            let idx = self.map.point2d_to_index(*pt);
+            self.map.tiles[idx] == TileType::Floor
+                && self.map.occupation[idx].is_none()
+                && !taken.contains(&idx)
+        });
+        // closest to the anchor first, so the group stays tight
+        candidates.sort_by(|a, b| {
+            let da = DistanceAlg::Pythagoras.distance2d(anchor_pt, *a);
+            let db = DistanceAlg::Pythagoras.distance2d(anchor_pt, *b);
+            da.partial_cmp(&db).unwrap()
+        });
+
+        candidates
+            .into_iter()
+            .take(count)
+            .map(Position::from)
+            .collect()
+    }
+
     fn count_neighbors(&self, x: i32, y: i32, map: &Map) -> usize {
         let mut neighbors = 0;
         for iy in -1..=1 {
@@ -252,6 +458,30 @@ impl MapBuilder {
         }
     }
 
+    // walls on the void next to walkable tiles, so floor never leads into nothing
+    fn wall_off_void(&mut self) {
+        let mut new_tiles = self.map.tiles.clone();
+        for (idx, tile) in self.map.tiles.iter().enumerate() {
+            if *tile != TileType::Void {
+                continue;
+            }
+            let pt = self.map.index_to_point2d(idx);
+            let next_to_floor = (-1..=1).any(|dy| {
+                (-1..=1).any(|dx| {
+                    self.map
+                        .try_idx(Position::new_from2d(pt.x + dx, pt.y + dy))
+                        .map_or(false, |n| {
+                            !matches!(self.map.tiles[n], TileType::Void | TileType::Wall)
+                        })
+                })
+            });
+            if next_to_floor {
+                new_tiles[idx] = TileType::Wall;
+            }
+        }
+        self.map.tiles = new_tiles;
+    }
+
     // replace tiles fully surrounded by walls with void tiles
     fn clean_walls_replace_with_void(&mut self) {
         let mut new_tiles = self.map.tiles.clone();
@@ -268,40 +498,158 @@ impl MapBuilder {
     }
 }
 
-pub fn build_map(mut commands: Commands, player_q: Query<&Player>) {
-    // start by getting the player, if it exists, to get the level
-    // if it doesnt exist, then it is level 0
-    let mut level = 0;
-    if player_q.iter().count() > 0 {
-        level = player_q.single().map_level;
-        // increase level by 1, because this system gets executed before the post_nextlevel
-        level += 1;
+// builds the map of a level, regenerating it until everything important can be reached
+fn generate_level(
+    level: LevelId,
+    graph: &DungeonGraph,
+    plans: &LevelPlans,
+    vaults: &Vaults,
+    rules: &MovementRules,
+) -> MapBuilder {
+    let plan = plans.get(level);
+    let mut rng = game_rng();
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let mut mb = MapBuilder::from_plan(plan, vaults);
+
+        // sometimes stamp a hand-made vault on top of the generated map
+        if rng.gen_range(0..100) < plan.vault_chance {
+            if let Some(vault) = vaults.random() {
+                mb.apply_vault(vault);
+            }
+        }
+
+        // levels with an exit goal get an exit tile, the others have an amulet or a boss.
+        // The bottom of a side branch has nowhere to go
+        if plan.goal == LevelGoal::Exit && graph.down(level).is_some() {
+            // replace more far away tile with an exit tile
+            let farer_position = mb.amulet_start;
+            let idx = mb.map.point2d_to_index(farer_position.into());
+            mb.map.tiles[idx] = TileType::Exit;
+        }
+
+        // the player arrives on stairs going back up, except on the first level
+        if graph.up(level).is_some() {
+            let idx = mb.map.point2d_to_index(mb.player_start.into());
+            mb.map.tiles[idx] = TileType::UpStairs;
+        }
+
+        for branch in graph.branches_from(level) {
+            let stairs = mb.find_branch_stairs();
+            let idx = mb.map.point2d_to_index(stairs.into());
+            mb.map.tiles[idx] = TileType::BranchStairs;
+            mb.branch_stairs.push((stairs, branch));
+        }
+
+        if let Some(theme) = plans.branch(level.branch).and_then(|b| b.theme.as_ref()) {
+            mb.theme = Theme::named(theme);
+        }
+
+        mb.update_regions();
+        let valid = mb.is_valid();
+        if valid || attempts >= MAX_MAP_ATTEMPTS {
+            if !valid {
+                println!("Warning: no valid map after {} attempts", attempts);
+            }
+            // generated without cutting corners, now it follows the game rules
+            mb.map.cut_corners = rules.cut_corners;
+            // decorations are picked once here, drawing the map never rolls the game generator
+            mb.map.decorations = mb.map.tiles.iter().map(|_| rng.gen_range(0..100)).collect();
+            return mb;
+        }
+    }
+}
+
+pub fn build_map(
+    mut commands: Commands,
+    plans: Res<LevelPlans>,
+    vaults: Res<Vaults>,
+    rules: Res<MovementRules>,
+    turn_state: Res<State<TurnState>>,
+    mut cache: ResMut<LevelCache>,
+    mut transition: ResMut<LevelTransition>,
+    mut graph: ResMut<DungeonGraph>,
+    current_mb: Option<ResMut<MapBuilder>>,
+    player_q: Query<&Position, With<Player>>,
+) {
+    // a new game starts from scratch
+    if *turn_state.get() != TurnState::NextLevel {
+        cache.levels.clear();
+        *transition = LevelTransition::default();
+        graph.current = LevelId::default();
+        commands.insert_resource(generate_level(
+            graph.current,
+            &graph,
+            &plans,
+            &vaults,
+            &rules,
+        ));
+        return;
     }
 
-    // create map
-    let mut mb = MapBuilder::new();
+    // this system gets executed before the player changes level, so it is still on the old one
+    let player_pos = player_q.single();
+    let level = transition.destination;
 
-    // during the first two level, place exit tile. last level will have amulet
-    if level < 2 {
-        // replace more far away tile with an exit tile
-        let farer_position = mb.amulet_start;
-        let idx = mb.map.point2d_to_index(farer_position.into());
-        mb.map.tiles[idx] = TileType::Exit;
+    // levels visited before come back as they were left
+    let mb = match cache.levels.remove(&level) {
+        Some(mb) => {
+            transition.restored = true;
+            mb
+        }
+        None => {
+            transition.restored = false;
+            generate_level(level, &graph, &plans, &vaults, &rules)
+        }
+    };
+
+    // keep the level being left, without the player in it
+    match current_mb {
+        Some(mut current_mb) => {
+            current_mb.free_occupy_tile(*player_pos);
+            let old_mb = std::mem::replace(&mut *current_mb, mb);
+            cache.levels.insert(graph.current, old_mb);
+        }
+        None => commands.insert_resource(mb),
     }
+}
 
-    // insert map builder as resource
-    commands.insert_resource(mb);
+// rules for stepping around the map, shared by the player and monsters
+#[derive(Resource, Clone, Copy)]
+pub struct MovementRules {
+    // if diagonal steps can go around the corner of a wall
+    pub cut_corners: bool,
 }
 
-pub struct MapPlugin;
+impl MovementRules {
+    pub fn from_args(args: &[String]) -> Self {
+        MovementRules {
+            cut_corners: !args.iter().any(|arg| arg == "--no-corner-cutting"),
+        }
+    }
+}
+
+pub struct MapPlugin {
+    pub rules: MovementRules,
+}
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut App) {
-        app
+        let plans = LevelPlans::load();
+        app.insert_resource(DungeonGraph::new(&plans))
+            .insert_resource(self.rules)
+            .insert_resource(plans)
+            .insert_resource(Vaults::load())
+            .init_resource::<LevelCache>()
+            .init_resource::<LevelTransition>()
             // when starting a new game
             .add_systems(OnEnter(TurnState::StartScreen), build_map)
             .add_systems(OnExit(TurnState::StartScreen), spawn_map_tiles)
-            // when advancing level
+            // when changing level
             .add_systems(OnEnter(TurnState::NextLevel), build_map)
-            .add_systems(OnExit(TurnState::NextLevel), spawn_map_tiles);
+            .add_systems(
+                OnExit(TurnState::NextLevel),
+                spawn_map_tiles.run_if(level_is_new),
+            );
     }
 }
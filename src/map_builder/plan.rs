@@ -0,0 +1,136 @@
+use crate::prelude::*;
+use ron::de::from_bytes;
+use serde::Deserialize;
+
+// which architect builds the level
+#[derive(Clone, Copy, Deserialize, Debug, PartialEq)]
+pub enum ArchitectKind {
+    Random,
+    Rooms,
+    Drunkard,
+    Automata,
+    Prefab,
+    WaveFunctionCollapse,
+    Voronoi,
+}
+
+// steps run in order on the map after the architect built it
+#[derive(Clone, Deserialize, Debug, PartialEq)]
+pub enum MapModifier {
+    // wear the walls down into floor, as many passes as given
+    Erode(usize),
+    // carve extra random rooms and tunnel them into the map
+    AddRooms(usize),
+    CullUnreachable,
+    TunnelUnreachable,
+    // stamp a vault by name, or a random one
+    Vault(Option<String>),
+    // pick again the spot for the exit or the amulet, the furthest from the player
+    PlaceExit,
+    // pick again the spawn points for monsters and items
+    PlaceSpawns,
+    // draw the level with a theme of the themes file, by name
+    Theme(String),
+}
+
+// what the player needs to do to finish the level
+#[derive(Clone, Copy, Deserialize, Debug, PartialEq)]
+pub enum LevelGoal {
+    // find the stairs down
+    Exit,
+    // grab the Amulet of Yala to win
+    Amulet,
+    // kill the level boss to win
+    KillBoss,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct LevelPlan {
+    pub architect: ArchitectKind,
+    pub goal: LevelGoal,
+    // name of a unique template spawned at the prefab boss marker
+    #[serde(default)]
+    pub boss: Option<String>,
+    // percentage chance of stamping a random vault into the level
+    #[serde(default)]
+    pub vault_chance: i32,
+    #[serde(default)]
+    pub modifiers: Vec<MapModifier>,
+}
+
+// side branch of the dungeon, entered from a second staircase of a main dungeon level
+#[derive(Clone, Deserialize, Debug)]
+pub struct BranchPlan {
+    pub name: String,
+    // level of the main dungeon with the stairs into the branch
+    pub entrance: u32,
+    // theme for all the levels of the branch
+    #[serde(default)]
+    pub theme: Option<String>,
+    // name of a template waiting at the bottom of the branch
+    #[serde(default)]
+    pub reward: Option<String>,
+    pub levels: Vec<LevelPlan>,
+}
+
+#[derive(Resource, Clone, Deserialize, Debug)]
+pub struct LevelPlans {
+    // the main dungeon
+    pub levels: Vec<LevelPlan>,
+    #[serde(default)]
+    pub branches: Vec<BranchPlan>,
+}
+
+static LEVELS: &[u8] = include_bytes!("../../assets/levels.ron");
+
+impl LevelPlans {
+    pub fn load() -> Self {
+        let plans: Self = from_bytes(LEVELS).expect("Unable to load level plans.");
+        plans.check();
+        plans
+    }
+
+    // plans that can't be played are an error in the levels file
+    fn check(&self) {
+        if self.levels.is_empty() {
+            panic!("The main dungeon has no levels.");
+        }
+        for branch in &self.branches {
+            if branch.levels.is_empty() {
+                panic!("The branch {} has no levels.", branch.name);
+            }
+        }
+
+        let main = self.levels.iter().map(|plan| ("the main dungeon", plan));
+        let branches = self.branches.iter().flat_map(|branch| {
+            branch
+                .levels
+                .iter()
+                .map(move |plan| (branch.name.as_str(), plan))
+        });
+        for (name, plan) in main.chain(branches) {
+            if plan.goal == LevelGoal::KillBoss && plan.boss.is_none() {
+                panic!("A level of {} has a KillBoss goal but no boss.", name);
+            }
+        }
+    }
+
+    // plan for a given level. Levels deeper than the plan reuse the last one
+    pub fn get(&self, level: LevelId) -> &LevelPlan {
+        let levels = match self.branch(level.branch) {
+            Some(branch) => &branch.levels,
+            None => &self.levels,
+        };
+        let idx = usize::min(level.depth as usize, levels.len() - 1);
+        &levels[idx]
+    }
+
+    // the plan of a side branch, None for the main dungeon
+    pub fn branch(&self, branch: usize) -> Option<&BranchPlan> {
+        if branch == MAIN_BRANCH {
+            None
+        } else {
+            self.branches.get(branch - 1)
+        }
+    }
+}
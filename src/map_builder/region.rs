@@ -0,0 +1,181 @@
+use crate::prelude::*;
+use bracket_geometry::prelude::Rect;
+use std::collections::{HashSet, VecDeque};
+
+// walkable tiles outside rooms per extra region, so caves get split too
+const TILES_PER_REGION: usize = 200;
+
+// an area of the map, like a room or a cave chamber
+#[derive(Clone, Debug)]
+pub struct Region {
+    pub bounds: Rect,
+    // how many walkable tiles it has
+    pub size: usize,
+    // the regions it opens into
+    pub connections: HashSet<usize>,
+}
+
+impl Default for Region {
+    fn default() -> Self {
+        Self {
+            bounds: Rect::with_size(0, 0, 0, 0),
+            size: 0,
+            connections: HashSet::new(),
+        }
+    }
+}
+
+// splits the walkable tiles of the map into regions
+#[derive(Clone, Debug, Default)]
+pub struct RegionMap {
+    // region of every tile, None for walls and void
+    pub tiles: Vec<Option<usize>>,
+    pub regions: Vec<Region>,
+}
+
+impl RegionMap {
+    pub fn region_at(&self, position: Position) -> Option<usize> {
+        if position.x < 0 || position.y < 0 || position.x >= SCREEN_WIDTH {
+            return None;
+        }
+        self.tiles
+            .get(map_idx(position.x, position.y))
+            .copied()
+            .flatten()
+    }
+
+    pub fn positions(&self, region: usize) -> Vec<Position> {
+        self.tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| **r == Some(region))
+            .map(|(idx, _)| {
+                Position::new_from2d(idx as i32 % SCREEN_WIDTH, idx as i32 / SCREEN_WIDTH)
+            })
+            .collect()
+    }
+}
+
+impl MapBuilder {
+    // brings the region map up to date with the tiles. Regions set by the architect are kept
+    // and grown over new floor. Without any, rooms become regions, plus random seeds for the
+    // floor outside rooms
+    pub fn update_regions(&mut self) {
+        let num_tiles = self.map.tiles.len();
+        let walkable: Vec<bool> = (0..num_tiles)
+            .map(|idx| self.map.can_enter_tile(self.map.index_to_point2d(idx)))
+            .collect();
+
+        if self.regions.tiles.len() != num_tiles {
+            self.regions = RegionMap {
+                tiles: vec![None; num_tiles],
+                regions: Vec::new(),
+            };
+        }
+        for (idx, region) in self.regions.tiles.iter_mut().enumerate() {
+            if !walkable[idx] {
+                *region = None;
+            }
+        }
+
+        if self.regions.tiles.iter().all(Option::is_none) {
+            self.regions.regions.clear();
+            for (id, room) in self.rooms.iter().enumerate() {
+                room.for_each(|pt| {
+                    if let Some(idx) = self.map.try_idx(pt.into()) {
+                        if walkable[idx] {
+                            self.regions.tiles[idx] = Some(id);
+                        }
+                    }
+                });
+                self.regions.regions.push(Region::default());
+            }
+            self.seed_random_regions(&walkable);
+        }
+
+        self.grow_regions(&walkable);
+        // whatever could not be reached by any region becomes a region of its own
+        while let Some(idx) =
+            (0..num_tiles).find(|idx| walkable[*idx] && self.regions.tiles[*idx].is_none())
+        {
+            self.regions.tiles[idx] = Some(self.regions.regions.len());
+            self.regions.regions.push(Region::default());
+            self.grow_regions(&walkable);
+        }
+
+        self.measure_regions();
+    }
+
+    fn seed_random_regions(&mut self, walkable: &[bool]) {
+        let mut rng = game_rng();
+        let mut candidates: Vec<usize> = (0..walkable.len())
+            .filter(|idx| walkable[*idx] && self.regions.tiles[*idx].is_none())
+            .collect();
+        for _ in 0..candidates.len() / TILES_PER_REGION {
+            let idx = candidates.remove(rng.gen_range(0..candidates.len()));
+            self.regions.tiles[idx] = Some(self.regions.regions.len());
+            self.regions.regions.push(Region::default());
+        }
+    }
+
+    // every region floods the walkable tiles next to it, closest region wins
+    fn grow_regions(&mut self, walkable: &[bool]) {
+        let mut open: VecDeque<usize> = (0..walkable.len())
+            .filter(|idx| self.regions.tiles[*idx].is_some())
+            .collect();
+        while let Some(idx) = open.pop_front() {
+            let pt = self.map.index_to_point2d(idx);
+            for delta in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let next = Position::new_from2d(pt.x + delta.0, pt.y + delta.1);
+                if let Some(next_idx) = self.map.try_idx(next) {
+                    if walkable[next_idx] && self.regions.tiles[next_idx].is_none() {
+                        self.regions.tiles[next_idx] = self.regions.tiles[idx];
+                        open.push_back(next_idx);
+                    }
+                }
+            }
+        }
+    }
+
+    // bounds, size and connections of every region
+    fn measure_regions(&mut self) {
+        let mut corners = vec![None; self.regions.regions.len()];
+        self.regions.regions.iter_mut().for_each(|region| {
+            region.size = 0;
+            region.connections.clear();
+        });
+
+        for (idx, region) in self.regions.tiles.iter().enumerate() {
+            let id = match region {
+                Some(id) => *id,
+                None => continue,
+            };
+            let pt = self.map.index_to_point2d(idx);
+            self.regions.regions[id].size += 1;
+            corners[id] = match corners[id] {
+                None => Some((pt, pt)),
+                Some((min, max)) => Some((
+                    Point::new(i32::min(min.x, pt.x), i32::min(min.y, pt.y)),
+                    Point::new(i32::max(max.x, pt.x), i32::max(max.y, pt.y)),
+                )),
+            };
+
+            // only looking right and down finds every pair once
+            for delta in [(1, 0), (0, 1)] {
+                let next = Position::new_from2d(pt.x + delta.0, pt.y + delta.1);
+                let other = self.map.try_idx(next).and_then(|i| self.regions.tiles[i]);
+                if let Some(other) = other.filter(|other| *other != id) {
+                    self.regions.regions[id].connections.insert(other);
+                    self.regions.regions[other].connections.insert(id);
+                }
+            }
+        }
+
+        for (region, corners) in self.regions.regions.iter_mut().zip(corners) {
+            region.bounds = match corners {
+                Some((min, max)) => Rect::with_exact(min.x, min.y, max.x + 1, max.y + 1),
+                None => Rect::with_size(0, 0, 0, 0),
+            };
+        }
+    }
+}
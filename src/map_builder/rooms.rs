@@ -1,4 +1,4 @@
-use super::MapArchitect;
+use super::{MapArchitect, NUM_ROOMS};
 use crate::prelude::*;
 
 pub struct RoomsArchitect {}
@@ -12,11 +12,16 @@ impl MapArchitect for RoomsArchitect {
             player_start: Position::new(0, 0, 0),
             enemies_start: Vec::new(),
             amulet_start: Position::new(0, 0, 0),
-            theme: super::themes::DungeonTheme::new(),
+            boss_start: None,
+            branch_stairs: Vec::new(),
+            monster_starts: Vec::new(),
+            item_starts: Vec::new(),
+            regions: RegionMap::default(),
+            theme: Theme::named("Dungeon"),
         };
 
         mb.fill(TileType::Void);
-        mb.build_random_rooms();
+        mb.build_random_rooms(NUM_ROOMS);
         mb.build_corridors();
         mb.player_start = Position::from(mb.rooms[0].center());
         mb.amulet_start = mb.find_most_distant();
@@ -26,4 +31,9 @@ impl MapArchitect for RoomsArchitect {
 
         mb
     }
+
+    // corridors only join rooms next to each other, so dig more instead of losing rooms
+    fn repair(&self) -> ConnectivityRepair {
+        ConnectivityRepair::Tunnel
+    }
 }
@@ -1,96 +1,113 @@
 use crate::prelude::*;
+use ron::de::from_bytes;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
 
+#[derive(Clone)]
 pub struct Glyph {
-    // the index in the atlas sprite sheet
-    pub index: usize,
+    // the logical sprite, found in the tileset in use
+    pub sprite: String,
     // the color to tint the glyph
     pub color: Color,
     // the background color. If the glyph uses the full cell, not needed
     pub bkg_color: Option<Color>,
 }
-impl Glyph {
-    fn new(index: usize, color: Color, bkg_color: Color) -> Self {
-        Self {
-            index,
-            color,
-            bkg_color: Some(bkg_color),
-        }
-    }
-    fn new_nobkg(index: usize, color: Color) -> Self {
-        Self {
-            index,
-            color,
-            bkg_color: None,
-        }
-    }
+
+// how a tile type is drawn, plus decorations that replace it now and then
+#[derive(Clone, Deserialize, Debug)]
+pub struct TileStyle {
+    pub sprite: String,
+    pub color: (f32, f32, f32),
+    #[serde(default)]
+    pub background: Option<(f32, f32, f32)>,
+    #[serde(default)]
+    pub variants: Vec<TileVariant>,
 }
 
-pub struct DungeonTheme {}
+// a decoration, drawn instead of the tile style in a percentage of the tiles
+#[derive(Clone, Deserialize, Debug)]
+pub struct TileVariant {
+    pub chance: i32,
+    pub style: TileStyle,
+}
 
-impl DungeonTheme {
-    pub fn new() -> Box<dyn MapTheme> {
-        Box::new(Self {})
+impl TileStyle {
+    fn glyph(&self) -> Glyph {
+        let (r, g, b) = self.color;
+        Glyph {
+            sprite: self.sprite.clone(),
+            color: Color::rgb(r, g, b),
+            bkg_color: self.background.map(|(r, g, b)| Color::rgb(r, g, b)),
+        }
     }
 }
 
-impl MapTheme for DungeonTheme {
-    fn tile_to_render(&self, tile_type: TileType) -> Option<Glyph> {
-        let glyph_color = Color::rgba(0.3, 0.3, 0.3, 1.0);
-        let wall_color = Color::rgba(0.05, 0.05, 0.05, 1.0);
-        let floor_color = Color::rgba(0.529, 0.529, 0.529, 1.0);
+#[derive(Clone, Deserialize, Debug)]
+pub struct ThemeData {
+    pub name: String,
+    // light reaching every tile, from 0 to 1
+    #[serde(default)]
+    pub ambient_light: f32,
+    // how many torches hang on the walls
+    #[serde(default)]
+    pub torches: usize,
+    pub tiles: HashMap<TileType, TileStyle>,
+}
 
-        match tile_type {
-            // index 219 is a full square
-            TileType::Floor => Some(Glyph::new_nobkg(219, floor_color)),
-            TileType::Wall => Some(Glyph::new('#' as usize, glyph_color, wall_color)),
-            TileType::Exit => Some(Glyph::new('>' as usize, glyph_color, floor_color)),
-            _ => None,
-        }
-    }
+#[derive(Deserialize, Debug)]
+struct Themes {
+    themes: Vec<ThemeData>,
 }
 
-pub struct ForestTheme {}
+static THEMES: &[u8] = include_bytes!("../../assets/themes.ron");
 
-impl MapTheme for ForestTheme {
-    fn tile_to_render(&self, tile_type: TileType) -> Option<Glyph> {
-        let glyph_color = Color::rgba(0.105, 0.470, 0.215, 1.0);
-        let cell_color = Color::rgba(0.352, 0.682, 0.380, 1.0);
+fn themes() -> &'static [ThemeData] {
+    static LOADED: OnceLock<Vec<ThemeData>> = OnceLock::new();
+    LOADED.get_or_init(|| {
+        from_bytes::<Themes>(THEMES)
+            .expect("Unable to load themes.")
+            .themes
+    })
+}
 
-        match tile_type {
-            // index 219 is a full square
-            TileType::Floor => Some(Glyph::new_nobkg(219, cell_color)),
-            TileType::Wall => Some(Glyph::new(30 as usize, glyph_color, cell_color)),
-            TileType::Exit => Some(Glyph::new('>' as usize, glyph_color, cell_color)),
-            _ => None,
-        }
-    }
+// a theme from the themes file
+pub struct Theme {
+    data: &'static ThemeData,
 }
 
-impl ForestTheme {
-    pub fn new() -> Box<dyn MapTheme> {
-        Box::new(Self {})
+impl Theme {
+    // unknown names get the first theme of the file
+    pub fn named(name: &str) -> Box<dyn MapTheme> {
+        let themes = themes();
+        let data = themes.iter().find(|t| t.name == name).unwrap_or_else(|| {
+            println!("Warning: there is no theme {}", name);
+            &themes[0]
+        });
+        Box::new(Self { data })
     }
 }
 
-pub struct CaveTheme {}
+impl MapTheme for Theme {
+    fn tile_to_render(&self, tile_type: TileType, decoration: i32) -> Option<Glyph> {
+        let style = self.data.tiles.get(&tile_type)?;
 
-impl MapTheme for CaveTheme {
-    fn tile_to_render(&self, tile_type: TileType) -> Option<Glyph> {
-        let glyph_color = Color::rgba(0.549, 0.317, 0.039, 1.0);
-        let cell_color = Color::rgba(0.749, 0.505, 0.176, 1.0);
-
-        match tile_type {
-            // index 219 is a full square
-            TileType::Floor => Some(Glyph::new_nobkg(219, cell_color)),
-            TileType::Wall => Some(Glyph::new(178 as usize, glyph_color, cell_color)),
-            TileType::Exit => Some(Glyph::new('>' as usize, glyph_color, cell_color)),
-            _ => None,
+        // at most one decoration per tile, each with its own chance
+        let mut threshold = 0;
+        for variant in style.variants.iter() {
+            threshold += variant.chance;
+            if decoration < threshold {
+                return Some(variant.style.glyph());
+            }
         }
+        Some(style.glyph())
+    }
+
+    fn ambient_light(&self) -> f32 {
+        self.data.ambient_light
     }
-}
 
-impl CaveTheme {
-    pub fn new() -> Box<dyn MapTheme> {
-        Box::new(Self {})
+    fn torches(&self) -> usize {
+        self.data.torches
     }
 }
@@ -0,0 +1,239 @@
+use crate::prelude::*;
+use bevy::render::mesh::Indices;
+use bevy::render::render_resource::PrimitiveTopology;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+
+// side of the square chunks the map is drawn in, in tiles
+const CHUNK_SIZE: i32 = 16;
+// sprite used to paint the tile backgrounds
+const BACKGROUND_SPRITE: &str = "background";
+// alpha of tiles revealed but out of the player field of view
+const REVEALED_ALPHA: f32 = 0.1;
+
+// a piece of the map drawn as a single mesh, with a textured quad per glyph
+#[derive(Component)]
+pub struct TileChunk {
+    // the first tile of the chunk
+    x: i32,
+    y: i32,
+    // the glyph of every tile in the chunk, to draw it again with another tileset
+    cells: Vec<(usize, Glyph)>,
+    // the tile and color of every quad of the mesh, in order
+    quads: Vec<(usize, Color)>,
+    // alpha every quad was last drawn with, to only touch the mesh when something changes
+    alphas: Vec<f32>,
+}
+
+impl TileChunk {
+    // builds the mesh of the chunk, tile backgrounds first so the glyphs get drawn over them
+    fn mesh(&mut self, tileset: &Tileset) -> Mesh {
+        let mut quads = Vec::new();
+        let mut positions = Vec::new();
+        let mut uvs = Vec::new();
+
+        // graphical tiles have their own background
+        let layers: &[bool] = if tileset.tinted {
+            &[true, false]
+        } else {
+            &[false]
+        };
+        for background in layers {
+            for (idx, glyph) in self.cells.iter() {
+                let (sprite, color) = match (background, glyph.bkg_color) {
+                    (true, Some(bkg_color)) => (BACKGROUND_SPRITE, bkg_color),
+                    (false, _) => (glyph.sprite.as_str(), glyph.color),
+                    (true, None) => continue,
+                };
+
+                let (dx, dy) = (
+                    (*idx as i32 % SCREEN_WIDTH - self.x) as f32,
+                    (*idx as i32 / SCREEN_WIDTH - self.y) as f32,
+                );
+                positions.extend([
+                    [dx - 0.5, dy - 0.5, 0.0],
+                    [dx + 0.5, dy - 0.5, 0.0],
+                    [dx + 0.5, dy + 0.5, 0.0],
+                    [dx - 0.5, dy + 0.5, 0.0],
+                ]);
+                uvs.extend(sprite_uvs(tileset, tileset.index(sprite)));
+                quads.push((*idx, tileset.tint(color)));
+            }
+        }
+
+        let indices = (0..quads.len() as u32)
+            .flat_map(|q| [q * 4, q * 4 + 1, q * 4 + 2, q * 4, q * 4 + 2, q * 4 + 3])
+            .collect();
+        self.alphas = vec![0.0; quads.len()];
+        self.quads = quads;
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, self.colors());
+        mesh.set_indices(Some(Indices::U32(indices)));
+        mesh
+    }
+
+    fn colors(&self) -> Vec<[f32; 4]> {
+        self.quads
+            .iter()
+            .zip(self.alphas.iter())
+            .flat_map(|((_, color), alpha)| [color.with_a(*alpha).as_linear_rgba_f32(); 4])
+            .collect()
+    }
+}
+
+// spawns the map as chunks of meshes, plus the entities marking the stairs
+pub fn spawn_map_tiles(
+    mut commands: Commands,
+    mb: Res<MapBuilder>,
+    atlas: Res<CharsetAsset>,
+    tilesets: Res<Tilesets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let material = materials.add(ColorMaterial {
+        color: Color::WHITE,
+        texture: Some(atlas.texture.clone()),
+    });
+
+    for chunk_y in (0..SCREEN_HEIGHT).step_by(CHUNK_SIZE as usize) {
+        for chunk_x in (0..SCREEN_WIDTH).step_by(CHUNK_SIZE as usize) {
+            let mut cells = Vec::new();
+            for y in chunk_y..i32::min(chunk_y + CHUNK_SIZE, SCREEN_HEIGHT) {
+                for x in chunk_x..i32::min(chunk_x + CHUNK_SIZE, SCREEN_WIDTH) {
+                    let idx = map_idx(x, y);
+                    let decoration = mb.map.decorations[idx];
+                    if let Some(glyph) = mb.theme.tile_to_render(mb.map.tiles[idx], decoration) {
+                        cells.push((idx, glyph));
+                    }
+                }
+            }
+
+            let mut chunk = TileChunk {
+                x: chunk_x,
+                y: chunk_y,
+                cells,
+                quads: Vec::new(),
+                alphas: Vec::new(),
+            };
+            let mesh = chunk.mesh(tilesets.current());
+
+            commands.spawn((
+                MaterialMesh2dBundle {
+                    mesh: meshes.add(mesh).into(),
+                    material: material.clone(),
+                    ..Default::default()
+                },
+                MapTile,
+                chunk,
+                Position {
+                    x: chunk_x,
+                    y: chunk_y,
+                    z: 0,
+                },
+                TileSize::square(1.0),
+            ));
+        }
+    }
+
+    // add entities with stairs components, so they are easy to find later
+    for (idx, tile) in mb.map.tiles.iter().enumerate() {
+        let pos: Position = (mb.map.index_to_point2d(idx), 1).into();
+        match tile {
+            TileType::Exit => {
+                commands.spawn((pos, ExitTile));
+            }
+            TileType::UpStairs => {
+                commands.spawn((pos, UpStairsTile));
+            }
+            TileType::BranchStairs => {
+                if let Some((_, branch)) = mb.branch_stairs.iter().find(|s| s.0 == pos) {
+                    commands.spawn((pos, BranchStairsTile { branch: *branch }));
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+// texture coordinates of a sprite, in the same order as the quad corners
+fn sprite_uvs(tileset: &Tileset, index: usize) -> [[f32; 2]; 4] {
+    // a little inset, so neighbor sprites never bleed in
+    const INSET: f32 = 1.0 / 1024.0;
+    let (width, height) = (1.0 / tileset.columns as f32, 1.0 / tileset.rows as f32);
+    let u0 = (index % tileset.columns) as f32 * width + INSET;
+    let v0 = (index / tileset.columns) as f32 * height + INSET;
+    let (u1, v1) = (u0 + width - 2.0 * INSET, v0 + height - 2.0 * INSET);
+    [[u0, v1], [u1, v1], [u1, v0], [u0, v0]]
+}
+
+// tiles in the player field of view are shown as lit as they are, the revealed ones are tinted
+// and the rest hidden. Only the chunks of the current level are updated, when something
+// they show changed
+pub fn update_tilemap(
+    mb: Res<MapBuilder>,
+    light_map: Res<LightMap>,
+    player_fov_q: Query<Ref<FieldOfView>, With<Player>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut chunks_q: Query<(&mut TileChunk, &Mesh2dHandle), Without<Stashed>>,
+    mut unstashed: RemovedComponents<Stashed>,
+) {
+    // chunks of a level the player comes back to
+    let restored = unstashed.read().count() > 0;
+    let player_fov = match player_fov_q.get_single() {
+        Ok(player_fov) => player_fov,
+        Err(_) => return,
+    };
+    if !mb.is_changed()
+        && !light_map.is_changed()
+        && !player_fov.is_changed()
+        && !chunks_q.iter_mut().any(|(chunk, _)| chunk.is_added())
+        && !restored
+    {
+        return;
+    }
+
+    for (mut chunk, mesh_handle) in chunks_q.iter_mut() {
+        let alphas: Vec<f32> = chunk
+            .quads
+            .iter()
+            .map(|(idx, _)| {
+                let pt = mb.map.index_to_point2d(*idx);
+                if player_fov.visible_tiles.contains(&pt) {
+                    light_map.alpha_at(pt)
+                } else if mb.map.revealed[*idx] {
+                    REVEALED_ALPHA
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+
+        if alphas == chunk.alphas {
+            continue;
+        }
+        chunk.alphas = alphas;
+        if let Some(mesh) = meshes.get_mut(&mesh_handle.0) {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, chunk.colors());
+        }
+    }
+}
+
+// draws every chunk again with the tileset in use, also the ones of other levels
+pub fn retile_map(
+    tilesets: Res<Tilesets>,
+    atlas: Res<CharsetAsset>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut chunks_q: Query<(&mut TileChunk, &Mesh2dHandle, &Handle<ColorMaterial>)>,
+) {
+    for (mut chunk, mesh_handle, material) in chunks_q.iter_mut() {
+        if let Some(mesh) = meshes.get_mut(&mesh_handle.0) {
+            *mesh = chunk.mesh(tilesets.current());
+        }
+        if let Some(material) = materials.get_mut(material) {
+            material.texture = Some(atlas.texture.clone());
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use crate::prelude::*;
+use bracket_geometry::prelude::Rect;
+use ron::de::from_bytes;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+// what a character of the vault layout turns into
+#[derive(Clone, Copy, Deserialize, Debug, PartialEq)]
+pub enum VaultCell {
+    Wall,
+    Floor,
+    Door,
+    Monster,
+    Item,
+    Boss,
+}
+
+// hand-made piece of map, stamped on top of generated levels
+#[derive(Clone, Deserialize, Debug)]
+pub struct Vault {
+    pub name: String,
+    pub legend: HashMap<char, VaultCell>,
+    pub layout: String,
+}
+
+impl Vault {
+    fn rows(&self) -> Vec<Vec<char>> {
+        self.layout
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .filter(|line| !line.is_empty())
+            .map(|line| line.chars().collect())
+            .collect()
+    }
+
+    pub fn width(&self) -> i32 {
+        self.rows().iter().map(Vec::len).max().unwrap_or(0) as i32
+    }
+
+    pub fn height(&self) -> i32 {
+        self.rows().len() as i32
+    }
+}
+
+// all the vaults of the vaults folder, and the samples used by wave function collapse,
+// which share the same file format
+#[derive(Resource, Clone, Default)]
+pub struct Vaults {
+    pub vaults: Vec<Vault>,
+    pub samples: Vec<Vault>,
+}
+
+// the files are built into the game, a new vault has to be listed here
+static VAULTS: &[(&str, &[u8])] = &[
+    (
+        "fortress.ron",
+        include_bytes!("../../assets/vaults/fortress.ron"),
+    ),
+    (
+        "pillared_hall.ron",
+        include_bytes!("../../assets/vaults/pillared_hall.ron"),
+    ),
+    (
+        "treasure_room.ron",
+        include_bytes!("../../assets/vaults/treasure_room.ron"),
+    ),
+];
+static SAMPLES: &[(&str, &[u8])] = &[(
+    "dungeon.ron",
+    include_bytes!("../../assets/wfc/dungeon.ron"),
+)];
+
+impl Vaults {
+    pub fn load() -> Self {
+        Self {
+            vaults: Self::load_files(VAULTS),
+            samples: Self::load_files(SAMPLES),
+        }
+    }
+
+    fn load_files(files: &[(&str, &[u8])]) -> Vec<Vault> {
+        files
+            .iter()
+            .map(|(name, bytes)| {
+                from_bytes(bytes).unwrap_or_else(|e| panic!("Unable to load vault {}: {}", name, e))
+            })
+            .collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Vault> {
+        self.vaults.iter().find(|v| v.name == name)
+    }
+
+    pub fn random(&self) -> Option<&Vault> {
+        Self::pick(&self.vaults)
+    }
+
+    pub fn random_sample(&self) -> Option<&Vault> {
+        Self::pick(&self.samples)
+    }
+
+    fn pick(vaults: &[Vault]) -> Option<&Vault> {
+        if vaults.is_empty() {
+            None
+        } else {
+            let mut rng = game_rng();
+            Some(&vaults[rng.gen_range(0..vaults.len())])
+        }
+    }
+}
+
+impl MapBuilder {
+    // stamps the vault somewhere reachable from the player, away from the start and the amulet.
+    // If the map ends up disconnected the stamp is undone. Returns if the vault was placed
+    pub fn apply_vault(&mut self, vault: &Vault) -> bool {
+        let (width, height) = (vault.width(), vault.height());
+        if width == 0 || width > SCREEN_WIDTH - 2 || height > SCREEN_HEIGHT - 2 {
+            return false;
+        }
+
+        let dijkstra_map = DijkstraMap::new(
+            SCREEN_WIDTH,
+            SCREEN_HEIGHT,
+            &[self.map.point2d_to_index(self.player_start.into())],
+            &self.map,
+            1024.0,
+        );
+
+        let mut rng = game_rng();
+        for _ in 0..10 {
+            // keep the outer boundary untouched
+            let dimensions = Rect::with_size(
+                rng.gen_range(1..SCREEN_WIDTH - width),
+                rng.gen_range(1..SCREEN_HEIGHT - height),
+                width,
+                height,
+            );
+
+            let points = dimensions.point_set();
+            if points.contains(&self.player_start.into())
+                || points.contains(&self.amulet_start.into())
+            {
+                continue;
+            }
+            // it needs to touch the reachable part of the map, but not too close to the player
+            let touches_map = points.iter().any(|pt| {
+                let distance = dijkstra_map.map[self.map.point2d_to_index(*pt)];
+                distance < 2000.0 && distance > 20.0
+            });
+            if !touches_map {
+                continue;
+            }
+
+            let old_tiles = self.map.tiles.clone();
+            let old_enemies = self.enemies_start.clone();
+            let old_monsters = self.monster_starts.clone();
+            let old_items = self.item_starts.clone();
+            let old_boss = self.boss_start;
+
+            self.enemies_start
+                .retain(|pt| !points.contains(&((*pt).into())));
+            self.stamp(vault, Point::new(dimensions.x1, dimensions.y1));
+
+            if self.vault_connected(vault, Point::new(dimensions.x1, dimensions.y1)) {
+                return true;
+            }
+
+            // undo, the vault broke the map
+            self.map.tiles = old_tiles;
+            self.enemies_start = old_enemies;
+            self.monster_starts = old_monsters;
+            self.item_starts = old_items;
+            self.boss_start = old_boss;
+        }
+        false
+    }
+
+    fn stamp(&mut self, vault: &Vault, placement: Point) {
+        for (dy, row) in vault.rows().iter().enumerate() {
+            for (dx, c) in row.iter().enumerate() {
+                let pos = Position::new_from2d(placement.x + dx as i32, placement.y + dy as i32);
+                let idx = map_idx(pos.x, pos.y);
+                match vault.legend.get(c) {
+                    Some(VaultCell::Wall) => self.map.tiles[idx] = TileType::Wall,
+                    Some(VaultCell::Floor) => self.map.tiles[idx] = TileType::Floor,
+                    Some(VaultCell::Door) => self.map.tiles[idx] = TileType::Door,
+                    Some(VaultCell::Monster) => {
+                        self.map.tiles[idx] = TileType::Floor;
+                        self.monster_starts.push(pos);
+                    }
+                    Some(VaultCell::Item) => {
+                        self.map.tiles[idx] = TileType::Floor;
+                        self.item_starts.push(pos);
+                    }
+                    Some(VaultCell::Boss) => {
+                        self.map.tiles[idx] = TileType::Floor;
+                        self.boss_start = Some(pos);
+                    }
+                    None => println!("No idea what to do with [{}] in {}", c, vault.name),
+                }
+            }
+        }
+
+        // close the vault against the void, so its floor never leads into nothing
+        let ring = Rect::with_exact(
+            placement.x - 1,
+            placement.y - 1,
+            placement.x + vault.width() + 1,
+            placement.y + vault.height() + 1,
+        );
+        ring.for_each(|pt| {
+            if let Some(idx) = self.map.try_idx(pt.into()) {
+                if self.map.tiles[idx] == TileType::Void {
+                    self.map.tiles[idx] = TileType::Wall;
+                }
+            }
+        });
+    }
+
+    // the amulet and every walkable tile of the vault must be reachable from the player
+    fn vault_connected(&self, vault: &Vault, placement: Point) -> bool {
+        let dijkstra_map = DijkstraMap::new(
+            SCREEN_WIDTH,
+            SCREEN_HEIGHT,
+            &[self.map.point2d_to_index(self.player_start.into())],
+            &self.map,
+            1024.0,
+        );
+        let reachable = |pt: Point| dijkstra_map.map[self.map.point2d_to_index(pt)] < f32::MAX;
+
+        if !reachable(self.amulet_start.into()) {
+            return false;
+        }
+
+        vault.rows().iter().enumerate().all(|(dy, row)| {
+            row.iter().enumerate().all(|(dx, _)| {
+                let pt = Point::new(placement.x + dx as i32, placement.y + dy as i32);
+                !self.map.can_enter_tile(pt) || reachable(pt)
+            })
+        })
+    }
+}
@@ -0,0 +1,153 @@
+use super::MapArchitect;
+use crate::prelude::*;
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+
+// how many chambers the map is split into
+const NUM_SEEDS: usize = 24;
+// percentage chance of an extra passage between chambers already joined, to make loops
+const EXTRA_PASSAGE_CHANCE: i32 = 20;
+// percentage chance of a passage being a door
+const DOOR_CHANCE: i32 = 25;
+
+// splits the map in voronoi cells around random seeds, every cell becomes a chamber
+pub struct VoronoiArchitect {}
+
+impl MapArchitect for VoronoiArchitect {
+    fn new(&mut self) -> MapBuilder {
+        let mut mb = MapBuilder {
+            map: Map::new(),
+            rooms: Vec::new(),
+            walls: Vec::new(),
+            player_start: Position::new(0, 0, 0),
+            enemies_start: Vec::new(),
+            amulet_start: Position::new(0, 0, 0),
+            boss_start: None,
+            branch_stairs: Vec::new(),
+            monster_starts: Vec::new(),
+            item_starts: Vec::new(),
+            regions: RegionMap::default(),
+            theme: Theme::named("Cave"),
+        };
+
+        let mut rng = game_rng();
+        let seeds: Vec<Point> = (0..NUM_SEEDS)
+            .map(|_| {
+                Point::new(
+                    rng.gen_range(1..SCREEN_WIDTH - 1),
+                    rng.gen_range(1..SCREEN_HEIGHT - 1),
+                )
+            })
+            .collect();
+        // the distance used changes the shape of the chambers
+        let distance = *[DistanceAlg::Pythagoras, DistanceAlg::Manhattan]
+            .choose(&mut rng)
+            .unwrap();
+
+        // every tile belongs to the closest seed
+        let owners: Vec<usize> = (0..mb.map.tiles.len())
+            .map(|idx| {
+                let pt = mb.map.index_to_point2d(idx);
+                seeds
+                    .iter()
+                    .enumerate()
+                    .map(|(seed, seed_pt)| (seed, distance.distance2d(pt, *seed_pt)))
+                    .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+                    .map_or(0, |(seed, _)| seed)
+            })
+            .collect();
+
+        // the border between two cells is a wall, the inside is floor
+        mb.fill(TileType::Wall);
+        mb.regions.tiles = vec![None; mb.map.tiles.len()];
+        mb.regions.regions = vec![Region::default(); NUM_SEEDS];
+        for y in 1..SCREEN_HEIGHT - 1 {
+            for x in 1..SCREEN_WIDTH - 1 {
+                let idx = map_idx(x, y);
+                let border = owners[map_idx(x + 1, y)] != owners[idx]
+                    || owners[map_idx(x, y + 1)] != owners[idx];
+                if !border {
+                    mb.map.tiles[idx] = TileType::Floor;
+                    mb.regions.tiles[idx] = Some(owners[idx]);
+                }
+            }
+        }
+
+        self.open_passages(&mut mb);
+        mb.clean_walls_replace_with_void();
+
+        let start = mb.closest_floor(seeds[0].into());
+        mb.player_start = start;
+        mb.amulet_start = mb.find_most_distant();
+        mb.enemies_start = mb.spawn_monsters(&start);
+        mb
+    }
+
+    // cells only touching at corners can't get a passage, dig those
+    fn repair(&self) -> ConnectivityRepair {
+        ConnectivityRepair::Tunnel
+    }
+}
+
+impl VoronoiArchitect {
+    // opens walls between chambers, enough to join all of them plus a few loops
+    fn open_passages(&self, mb: &mut MapBuilder) {
+        let mut rng = game_rng();
+
+        // walls with floor of two different chambers at both sides, by pair of chambers
+        let mut candidates: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for y in 1..SCREEN_HEIGHT - 1 {
+            for x in 1..SCREEN_WIDTH - 1 {
+                let idx = map_idx(x, y);
+                if mb.map.tiles[idx] != TileType::Wall {
+                    continue;
+                }
+                for (a, b) in [
+                    (map_idx(x - 1, y), map_idx(x + 1, y)),
+                    (map_idx(x, y - 1), map_idx(x, y + 1)),
+                ] {
+                    if let (Some(ra), Some(rb)) = (mb.regions.tiles[a], mb.regions.tiles[b]) {
+                        if ra != rb {
+                            candidates
+                                .entry((usize::min(ra, rb), usize::max(ra, rb)))
+                                .or_default()
+                                .push(idx);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut pairs: Vec<(usize, usize)> = candidates.keys().copied().collect();
+        pairs.sort();
+        pairs.shuffle(&mut rng);
+
+        // union find of the joined chambers
+        let mut parent: Vec<usize> = (0..NUM_SEEDS).collect();
+        fn find(parent: &mut Vec<usize>, id: usize) -> usize {
+            let mut root = id;
+            while parent[root] != root {
+                root = parent[root];
+            }
+            parent[id] = root;
+            root
+        }
+
+        for (a, b) in pairs {
+            let (root_a, root_b) = (find(&mut parent, a), find(&mut parent, b));
+            if root_a == root_b && rng.gen_range(0..100) >= EXTRA_PASSAGE_CHANCE {
+                continue;
+            }
+            parent[root_a] = root_b;
+
+            let walls = &candidates[&(a, b)];
+            let idx = walls[rng.gen_range(0..walls.len())];
+            mb.map.tiles[idx] = if rng.gen_range(0..100) < DOOR_CHANCE {
+                TileType::Door
+            } else {
+                TileType::Floor
+            };
+            mb.regions.tiles[idx] = Some(a);
+        }
+    }
+}
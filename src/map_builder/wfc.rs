@@ -0,0 +1,328 @@
+use super::rooms::RoomsArchitect;
+use super::MapArchitect;
+use crate::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+// size of the patterns taken from the sample
+const PATTERN_SIZE: usize = 3;
+// attempts to collapse the wave before giving up and using another architect
+const MAX_TRIES: usize = 5;
+const WIDTH: usize = SCREEN_WIDTH as usize;
+const HEIGHT: usize = SCREEN_HEIGHT as usize;
+// neighbors as (dx, dy). The opposite of direction d is d ^ 1
+const DIRECTIONS: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+// builds levels with Wave Function Collapse (overlapping model) from a sample vault
+pub struct WaveFunctionCollapseArchitect {
+    pub sample: Option<Vault>,
+    fell_back: bool,
+}
+
+impl WaveFunctionCollapseArchitect {
+    pub fn new(sample: Option<Vault>) -> Self {
+        Self {
+            sample,
+            fell_back: false,
+        }
+    }
+}
+
+impl MapArchitect for WaveFunctionCollapseArchitect {
+    fn new(&mut self) -> MapBuilder {
+        let tiles = self.sample.as_ref().and_then(|sample| {
+            let patterns = Patterns::from_sample(&sample_tiles(sample));
+            (0..MAX_TRIES).find_map(|_| patterns.collapse())
+        });
+
+        let tiles = match tiles {
+            Some(tiles) => tiles,
+            None => {
+                println!("Warning: wave function collapse did not converge, using rooms");
+                self.fell_back = true;
+                return RoomsArchitect {}.new();
+            }
+        };
+
+        let mut mb = MapBuilder {
+            map: Map::new(),
+            rooms: Vec::new(),
+            walls: Vec::new(),
+            player_start: Position::new(0, 0, 0),
+            enemies_start: Vec::new(),
+            amulet_start: Position::new(0, 0, 0),
+            boss_start: None,
+            branch_stairs: Vec::new(),
+            monster_starts: Vec::new(),
+            item_starts: Vec::new(),
+            regions: RegionMap::default(),
+            theme: Theme::named("Dungeon"),
+        };
+
+        mb.map.tiles = tiles;
+        mb.wall_around_boundary();
+        mb.clean_walls_replace_with_void();
+        let start = mb.closest_floor(Position::new_from2d(SCREEN_WIDTH / 2, SCREEN_HEIGHT / 2));
+        mb.player_start = start;
+        mb.amulet_start = mb.find_most_distant();
+        mb.enemies_start = mb.spawn_monsters(&start);
+        mb
+    }
+
+    fn repair(&self) -> ConnectivityRepair {
+        if self.fell_back {
+            ConnectivityRepair::Tunnel
+        } else {
+            ConnectivityRepair::Cull
+        }
+    }
+}
+
+// the sample as a grid of tiles, markers are just floor
+fn sample_tiles(sample: &Vault) -> Vec<Vec<TileType>> {
+    sample
+        .layout
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            line.trim_end_matches('\r')
+                .chars()
+                .map(|c| match sample.legend.get(&c) {
+                    Some(VaultCell::Wall) | None => TileType::Wall,
+                    Some(VaultCell::Door) => TileType::Door,
+                    Some(_) => TileType::Floor,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// every pattern found in the sample, how often, and which ones fit next to each other
+struct Patterns {
+    tiles: Vec<Vec<TileType>>,
+    weights: Vec<f32>,
+    // compatible[d][p] lists the patterns that can be placed in direction d of pattern p
+    compatible: [Vec<Vec<usize>>; 4],
+}
+
+impl Patterns {
+    fn from_sample(sample: &[Vec<TileType>]) -> Self {
+        // mirrored copies of the sample give more patterns and help convergence
+        let flipped_x: Vec<Vec<TileType>> = sample
+            .iter()
+            .map(|row| row.iter().rev().copied().collect())
+            .collect();
+        let flipped_y: Vec<Vec<TileType>> = sample.iter().rev().cloned().collect();
+
+        let mut tiles: Vec<Vec<TileType>> = Vec::new();
+        let mut weights: Vec<f32> = Vec::new();
+        for grid in [sample.to_vec(), flipped_x, flipped_y] {
+            let height = grid.len();
+            let width = grid.iter().map(Vec::len).min().unwrap_or(0);
+            for y in 0..(height + 1).saturating_sub(PATTERN_SIZE) {
+                for x in 0..(width + 1).saturating_sub(PATTERN_SIZE) {
+                    let pattern: Vec<TileType> = (0..PATTERN_SIZE * PATTERN_SIZE)
+                        .map(|i| grid[y + i / PATTERN_SIZE][x + i % PATTERN_SIZE])
+                        .collect();
+                    match tiles.iter().position(|p| *p == pattern) {
+                        Some(existing) => weights[existing] += 1.0,
+                        None => {
+                            tiles.push(pattern);
+                            weights.push(1.0);
+                        }
+                    }
+                }
+            }
+        }
+
+        let compatible = [0, 1, 2, 3].map(|d| {
+            let (dx, dy) = DIRECTIONS[d];
+            (0..tiles.len())
+                .map(|p| {
+                    (0..tiles.len())
+                        .filter(|q| overlaps(&tiles[p], &tiles[*q], dx, dy))
+                        .collect()
+                })
+                .collect()
+        });
+
+        Self {
+            tiles,
+            weights,
+            compatible,
+        }
+    }
+
+    // tries to collapse the whole map. None if it reached a contradiction
+    fn collapse(&self) -> Option<Vec<TileType>> {
+        if self.tiles.is_empty() {
+            return None;
+        }
+        let mut rng = game_rng();
+        let mut wave = Wave::new(self);
+
+        // cells by fewest options, with random noise to break ties. Entries go stale when the
+        // count of a cell changes, a fresh one is pushed then
+        let noise: Vec<u32> = (0..wave.counts.len()).map(|_| rng.gen()).collect();
+        let mut heap: BinaryHeap<Reverse<(usize, u32, usize)>> = (0..wave.counts.len())
+            .map(|cell| Reverse((wave.counts[cell], noise[cell], cell)))
+            .collect();
+
+        loop {
+            // observe the undecided cell with the fewest options left
+            let mut cell = None;
+            while let Some(Reverse((count, _, candidate))) = heap.pop() {
+                if count == wave.counts[candidate] && count > 1 {
+                    cell = Some(candidate);
+                    break;
+                }
+            }
+
+            let cell = match cell {
+                Some(cell) => cell,
+                // everything decided
+                None => break,
+            };
+
+            let options: Vec<usize> = wave.options(cell).collect();
+            let total: f32 = options.iter().map(|p| self.weights[*p]).sum();
+            let mut roll = rng.gen::<f32>() * total;
+            let mut chosen = options[options.len() - 1];
+            for p in options.iter() {
+                roll -= self.weights[*p];
+                if roll <= 0.0 {
+                    chosen = *p;
+                    break;
+                }
+            }
+
+            for p in options.into_iter().filter(|p| *p != chosen) {
+                wave.ban(cell, p);
+            }
+            let changed = wave.propagate(self)?;
+            changed
+                .into_iter()
+                .for_each(|cell| heap.push(Reverse((wave.counts[cell], noise[cell], cell))));
+        }
+
+        // every tile takes the top left of the pattern covering it
+        let mut tiles = vec![TileType::Void; WIDTH * HEIGHT];
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let (cx, cy) = (
+                    usize::min(x, wave.cells_x - 1),
+                    usize::min(y, wave.cells_y - 1),
+                );
+                let pattern = wave.options(cy * wave.cells_x + cx).next()?;
+                let (px, py) = (x - cx, y - cy);
+                tiles[y * WIDTH + x] = self.tiles[pattern][py * PATTERN_SIZE + px];
+            }
+        }
+        Some(tiles)
+    }
+}
+
+// which patterns are still possible in every cell of the output
+struct Wave {
+    cells_x: usize,
+    cells_y: usize,
+    patterns: usize,
+    possible: Vec<bool>,
+    counts: Vec<usize>,
+    // for every cell, pattern and direction, how many patterns in that neighbor still allow it
+    supports: Vec<usize>,
+    // bans waiting to be propagated
+    pending: Vec<(usize, usize)>,
+}
+
+impl Wave {
+    fn new(patterns: &Patterns) -> Self {
+        let cells_x = WIDTH - PATTERN_SIZE + 1;
+        let cells_y = HEIGHT - PATTERN_SIZE + 1;
+        let n = patterns.tiles.len();
+
+        let mut supports = Vec::with_capacity(cells_x * cells_y * n * 4);
+        for _ in 0..cells_x * cells_y {
+            for p in 0..n {
+                for d in 0..4 {
+                    supports.push(patterns.compatible[d][p].len());
+                }
+            }
+        }
+
+        Self {
+            cells_x,
+            cells_y,
+            patterns: n,
+            possible: vec![true; cells_x * cells_y * n],
+            counts: vec![n; cells_x * cells_y],
+            supports,
+            pending: Vec::new(),
+        }
+    }
+
+    fn options(&self, cell: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..self.patterns).filter(move |p| self.possible[cell * self.patterns + p])
+    }
+
+    fn ban(&mut self, cell: usize, pattern: usize) {
+        self.possible[cell * self.patterns + pattern] = false;
+        self.counts[cell] -= 1;
+        self.pending.push((cell, pattern));
+    }
+
+    fn neighbor(&self, cell: usize, d: usize) -> Option<usize> {
+        let (dx, dy) = DIRECTIONS[d];
+        let x = (cell % self.cells_x) as i32 + dx;
+        let y = (cell / self.cells_x) as i32 + dy;
+        if x < 0 || y < 0 || x >= self.cells_x as i32 || y >= self.cells_y as i32 {
+            None
+        } else {
+            Some(y as usize * self.cells_x + x as usize)
+        }
+    }
+
+    // removes the options left without support by the bans. Returns the cells that changed,
+    // or None on contradiction
+    fn propagate(&mut self, patterns: &Patterns) -> Option<Vec<usize>> {
+        let mut changed = Vec::new();
+        while let Some((cell, banned)) = self.pending.pop() {
+            if self.counts[cell] == 0 {
+                return None;
+            }
+            changed.push(cell);
+
+            for d in 0..4 {
+                // the cell that has this one in direction d of it
+                let other = match self.neighbor(cell, d ^ 1) {
+                    Some(other) => other,
+                    None => continue,
+                };
+                // patterns p over there that allowed the banned one in direction d
+                for p in patterns.compatible[d ^ 1][banned].iter() {
+                    let support = (other * self.patterns + p) * 4 + d;
+                    self.supports[support] -= 1;
+                    if self.supports[support] == 0 && self.possible[other * self.patterns + p] {
+                        self.ban(other, *p);
+                    }
+                }
+            }
+        }
+        Some(changed)
+    }
+}
+
+// true if pattern q, moved by (dx, dy), agrees with pattern p where they overlap
+fn overlaps(p: &[TileType], q: &[TileType], dx: i32, dy: i32) -> bool {
+    let n = PATTERN_SIZE as i32;
+    for y in i32::max(0, dy)..i32::min(n, n + dy) {
+        for x in i32::max(0, dx)..i32::min(n, n + dx) {
+            let a = p[(y * n + x) as usize];
+            let b = q[((y - dy) * n + (x - dx)) as usize];
+            if a != b {
+                return false;
+            }
+        }
+    }
+    true
+}
@@ -1,17 +1,35 @@
 use crate::prelude::*;
+use bevy::window::WindowResized;
 
+// moves up to this many tiles away are animated
+const MAX_TWEEN_TILES: f32 = 2.5;
+
+// only entities with a new or changed tile size are scaled, unless the window was resized
 pub fn size_scaling(
     primary_query: Query<&Window, With<PrimaryWindow>>,
-    mut q: Query<(&TileSize, &mut Transform)>,
+    mut resized: EventReader<WindowResized>,
+    mut q: ParamSet<(
+        Query<(&TileSize, &mut Transform), Or<(Changed<TileSize>, Added<Transform>)>>,
+        Query<(&TileSize, &mut Transform)>,
+    )>,
 ) {
+    let window_resized = resized.read().count() > 0;
     if let Ok(primary) = primary_query.get_single() {
-        for (sprite_size, mut transform) in q.iter_mut() {
-            let scale = Vec3::new(
+        let scale = |sprite_size: &TileSize| {
+            Vec3::new(
                 sprite_size.width / SCREEN_WIDTH as f32 * primary.width() as f32,
                 sprite_size.height / SCREEN_HEIGHT as f32 * primary.height() as f32,
                 1.0,
-            );
-            transform.scale = scale;
+            )
+        };
+        if window_resized {
+            for (sprite_size, mut transform) in q.p1().iter_mut() {
+                transform.scale = scale(sprite_size);
+            }
+        } else {
+            for (sprite_size, mut transform) in q.p0().iter_mut() {
+                transform.scale = scale(sprite_size);
+            }
         }
     }
 }
@@ -21,13 +39,22 @@ pub fn convert_pos(pos: f32, bound_window: f32, bound_game: f32) -> f32 {
     pos / bound_game * bound_window - (bound_window / 2.) + (tile_size / 2.)
 }
 
+// only entities that moved or just got a transform are placed, unless the window was resized.
+// Steps to a neighbor tile slide there when animations are on
 pub fn position_translation(
-    primary_query: Query<&Window>,
-    mut q: Query<(&Position, &mut Transform)>,
+    mut commands: Commands,
+    settings: Option<Res<AnimationSettings>>,
+    primary_query: Query<&Window, With<PrimaryWindow>>,
+    mut resized: EventReader<WindowResized>,
+    mut q: ParamSet<(
+        Query<(Entity, &Position, &mut Transform), Or<(Changed<Position>, Added<Transform>)>>,
+        Query<(Entity, &Position, &mut Transform)>,
+    )>,
 ) {
+    let window_resized = resized.read().count() > 0;
     if let Ok(primary) = primary_query.get_single() {
-        for (pos, mut transform) in q.iter_mut() {
-            transform.translation = Vec3::new(
+        let translation = |pos: &Position| {
+            Vec3::new(
                 convert_pos(pos.x as f32, primary.width() as f32, SCREEN_WIDTH as f32),
                 convert_pos(
                     (pos.y + UI_HEIGHT / 2) as f32,
@@ -35,7 +62,31 @@ pub fn position_translation(
                     SCREEN_HEIGHT as f32,
                 ),
                 pos.z as f32,
-            );
+            )
+        };
+        let animate = settings.map_or(false, |settings| settings.enabled);
+        let tile_size = primary.width() / SCREEN_WIDTH as f32;
+        if window_resized {
+            for (entity, pos, mut transform) in q.p1().iter_mut() {
+                transform.translation = translation(pos);
+                commands.entity(entity).remove::<Tween>();
+            }
+        } else {
+            for (entity, pos, mut transform) in q.p0().iter_mut() {
+                let to = translation(pos);
+                // anything further away, like a new level, is a jump
+                let step = transform.translation.distance(to) <= MAX_TWEEN_TILES * tile_size;
+                if animate && step && !transform.is_added() {
+                    commands.entity(entity).insert(Tween {
+                        from: transform.translation,
+                        to,
+                        elapsed: 0.0,
+                    });
+                } else {
+                    transform.translation = to;
+                    commands.entity(entity).remove::<Tween>();
+                }
+            }
         }
     }
 }
@@ -0,0 +1,198 @@
+use crate::prelude::*;
+use bevy::app::AppExit;
+use bevy::input::InputSystem;
+use ron::ser::{to_string_pretty, PrettyConfig};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+// where the actions of a game get saved when no --record is given
+const DEFAULT_REPLAY_FILE: &str = "last_run.ron";
+// seconds between two replayed actions, at normal speed
+const REPLAY_STEP_TIME: f32 = 0.2;
+// faster than this, the game clock spends frames catching up its fixed steps
+const MAX_REPLAY_SPEED: f32 = 100.0;
+
+// a game, as the seed of the dungeon and what the player did in it. Opening the map or
+// switching the tileset only change how the game looks, and are not recorded
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    // the movement rules change how monsters walk, replays older than them cut corners
+    #[serde(default = "cuts_corners")]
+    pub cut_corners: bool,
+    pub actions: Vec<PlayerAction>,
+}
+
+fn cuts_corners() -> bool {
+    true
+}
+
+impl Replay {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        ron::from_str(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+}
+
+#[derive(Resource)]
+struct Recorder {
+    path: String,
+    replay: Replay,
+}
+
+// only the actions the game applied are recorded, not the ones dropped. The file gets
+// written after every action, so it is there even if the game crashes
+fn record_actions(mut recorder: ResMut<Recorder>, mut actions: EventReader<ResolvedAction>) {
+    let before = recorder.replay.actions.len();
+    recorder.replay.actions.extend(
+        actions
+            .read()
+            .map(|resolved| resolved.0)
+            .filter(|action| action.changes_game()),
+    );
+    if recorder.replay.actions.len() == before {
+        return;
+    }
+    let saved = to_string_pretty(&recorder.replay, PrettyConfig::default())
+        .map_err(|e| e.to_string())
+        .and_then(|text| fs::write(&recorder.path, text).map_err(|e| e.to_string()));
+    if let Err(e) = saved {
+        println!(
+            "Warning: unable to save the replay to {}: {}",
+            recorder.path, e
+        );
+    }
+}
+
+#[derive(Resource)]
+struct Playback {
+    actions: Vec<PlayerAction>,
+    next: usize,
+    // waits between actions, on the game clock that runs faster with --speed
+    timer: Timer,
+    speed: f32,
+}
+
+// sends the recorded actions, like the player did. Screens are skipped right away,
+// and when the replay is over the player takes control
+fn play_actions(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut playback: ResMut<Playback>,
+    mut actions: EventWriter<PlayerAction>,
+    mut exit: EventWriter<AppExit>,
+    time: Res<Time>,
+    turn_state: Res<State<TurnState>>,
+    next_turn_state: Res<NextState<TurnState>>,
+) {
+    if playback.next >= playback.actions.len() {
+        return;
+    }
+
+    // the player keys are ignored while watching, except to quit
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        exit.send(AppExit);
+    }
+    keyboard_input.reset_all();
+    playback.timer.tick(time.delta());
+    // the state changes after this frame input, wait for it
+    if next_turn_state.0.is_some() {
+        return;
+    }
+
+    match turn_state.get() {
+        TurnState::StartScreen
+        | TurnState::NextLevel
+        | TurnState::GameOver
+        | TurnState::Victory => {
+            keyboard_input.press(KeyCode::Space);
+        }
+        TurnState::AwaitingInput if playback.timer.finished() => {
+            actions.send(playback.actions[playback.next]);
+            playback.next += 1;
+            playback.timer.reset();
+            if playback.next == playback.actions.len() {
+                println!("Replay finished.");
+            }
+        }
+        _ => (),
+    }
+}
+
+fn set_replay_speed(playback: Res<Playback>, mut time: ResMut<Time<Virtual>>) {
+    time.set_relative_speed(playback.speed);
+}
+
+// records the actions of the game to a file, or plays back the ones of a file
+pub enum ReplayPlugin {
+    Record { path: String, seed: u64 },
+    Play { replay: Replay, speed: f32 },
+}
+
+impl ReplayPlugin {
+    // --replay <file> plays a game back, at --speed times the normal speed. Otherwise the
+    // game is recorded to --record <file>, or last_run.ron
+    pub fn from_args(args: &[String]) -> Self {
+        let value = |flag: &str| {
+            args.iter()
+                .position(|arg| arg == flag)
+                .and_then(|i| args.get(i + 1))
+        };
+
+        match value("--replay") {
+            Some(path) => {
+                let replay = Replay::load(path).unwrap_or_else(|e| {
+                    println!("Error: unable to load the replay {}", e);
+                    std::process::exit(2);
+                });
+                seed_rng(replay.seed);
+                let speed = value("--speed")
+                    .and_then(|speed| speed.parse().ok())
+                    .filter(|speed: &f32| *speed > 0.0)
+                    .map_or(1.0, |speed| speed.min(MAX_REPLAY_SPEED));
+                Self::Play { replay, speed }
+            }
+            None => {
+                let seed = seed_from_args(args);
+                let path = value("--record").map_or(DEFAULT_REPLAY_FILE, |path| path.as_str());
+                println!("Recording seed {} to {}", seed, path);
+                Self::Record {
+                    path: path.to_string(),
+                    seed,
+                }
+            }
+        }
+    }
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        match self {
+            Self::Record { path, seed } => {
+                let rules = *app.world.resource::<MovementRules>();
+                app.insert_resource(Recorder {
+                    path: path.clone(),
+                    replay: Replay {
+                        seed: *seed,
+                        cut_corners: rules.cut_corners,
+                        actions: Vec::new(),
+                    },
+                })
+                .add_systems(Last, record_actions);
+            }
+            Self::Play { replay, speed } => {
+                // the game gets played with the rules it was recorded with
+                app.insert_resource(MovementRules {
+                    cut_corners: replay.cut_corners,
+                })
+                .insert_resource(Playback {
+                    actions: replay.actions.clone(),
+                    next: 0,
+                    timer: Timer::from_seconds(REPLAY_STEP_TIME, TimerMode::Once),
+                    speed: *speed,
+                })
+                .add_systems(Startup, set_replay_speed)
+                .add_systems(PreUpdate, play_actions.after(InputSystem));
+            }
+        }
+    }
+}
@@ -1,9 +1,12 @@
 use crate::prelude::*;
+use std::collections::HashSet;
 
 // resource type
 #[derive(Resource)]
 pub struct CharsetAsset {
     pub atlas: Handle<TextureAtlas>,
+    // the charset image itself, for meshes drawing glyphs
+    pub texture: Handle<Image>,
 }
 
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
@@ -12,6 +15,8 @@ pub enum TurnState {
     StartScreen,
     AwaitingInput,
     InMenus,
+    // examining the map with a cursor
+    Looking,
     PlayerTurn,
     MonsterTurn,
     GameOver,
@@ -25,6 +30,7 @@ pub enum PopUpState {
     None,
     EquipmentPopup,
     InventoryPopup,
+    LevelMap,
 }
 
 #[derive(Resource)]
@@ -49,3 +55,55 @@ impl GameLog {
         self.entries.remove(0);
     }
 }
+
+// how the player is moving between levels
+#[derive(Resource, Default)]
+pub struct LevelTransition {
+    // the level the player leaves and the one it goes to
+    pub origin: LevelId,
+    pub destination: LevelId,
+    // taking the stairs up instead of down
+    pub going_up: bool,
+    // the level being entered was visited before and comes from the cache
+    pub restored: bool,
+    // where the player arrived. Stairs there don't trigger until the player steps off
+    pub arrival: Option<Position>,
+}
+
+// how much light reaches every tile of the current level, from 0 to 1
+#[derive(Resource, Default)]
+pub struct LightMap {
+    pub levels: Vec<f32>,
+}
+
+// light needed to see a tile further than next to the player
+pub const LIGHT_THRESHOLD: f32 = 0.05;
+// alpha of the darkest tiles and entities the player can see
+const MIN_LIGHT_ALPHA: f32 = 0.35;
+
+impl LightMap {
+    pub fn light_at(&self, pt: Point) -> f32 {
+        if pt.x < 0 || pt.x >= SCREEN_WIDTH || pt.y < 0 || pt.y >= SCREEN_HEIGHT {
+            return 0.0;
+        }
+        self.levels.get(map_idx(pt.x, pt.y)).copied().unwrap_or(0.0)
+    }
+
+    pub fn is_lit(&self, pt: Point) -> bool {
+        self.light_at(pt) >= LIGHT_THRESHOLD
+    }
+
+    // visible things are tinted by how lit they are
+    pub fn alpha_at(&self, pt: Point) -> f32 {
+        MIN_LIGHT_ALPHA + (1.0 - MIN_LIGHT_ALPHA) * self.light_at(pt)
+    }
+}
+
+// things that need to be remembered for the whole run, across levels
+#[derive(Resource, Default)]
+pub struct RunState {
+    // unique templates already spawned, so they only appear once per run
+    pub spawned_uniques: HashSet<String>,
+    // unique templates killed by the player
+    pub defeated_uniques: HashSet<String>,
+}
@@ -0,0 +1,54 @@
+use rand::rngs::StdRng;
+use rand::{Error, RngCore, SeedableRng};
+use std::sync::Mutex;
+
+// one random generator for the whole game, so a seed gives back the same dungeons and fights
+static RNG: Mutex<Option<StdRng>> = Mutex::new(None);
+
+pub fn seed_rng(seed: u64) {
+    *RNG.lock().unwrap() = Some(StdRng::seed_from_u64(seed));
+}
+
+// the seed passed with --seed, or a random one. The game generator starts from it
+pub fn seed_from_args(args: &[String]) -> u64 {
+    let seed = args
+        .iter()
+        .position(|arg| arg == "--seed")
+        .and_then(|i| args.get(i + 1))
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or_else(rand::random);
+    seed_rng(seed);
+    seed
+}
+
+// a handle to the game generator, used like game_rng()
+pub struct GameRng;
+
+pub fn game_rng() -> GameRng {
+    GameRng
+}
+
+impl GameRng {
+    fn with<T>(f: impl FnOnce(&mut StdRng) -> T) -> T {
+        let mut rng = RNG.lock().unwrap();
+        f(rng.get_or_insert_with(StdRng::from_entropy))
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        Self::with(|rng| rng.next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        Self::with(|rng| rng.next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        Self::with(|rng| rng.fill_bytes(dest));
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        Self::with(|rng| rng.try_fill_bytes(dest))
+    }
+}
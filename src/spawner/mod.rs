@@ -2,39 +2,111 @@ use crate::prelude::*;
 mod template;
 use template::Templates;
 
+const TORCH_RADIUS: i32 = 6;
+const TORCH_INTENSITY: f32 = 0.8;
+
 pub fn spawn_level(
     mut commands: Commands,
     atlas: Res<CharsetAsset>,
     mut mb: ResMut<MapBuilder>,
-    player_q: Query<&Player>,
+    plans: Res<LevelPlans>,
+    tilesets: Res<Tilesets>,
+    mut run_state: ResMut<RunState>,
+    graph: Res<DungeonGraph>,
 ) {
-    // start by getting the player, if it exists, to get the level
-    // if it doesnt exist, then it is level 0
-    let mut level = 0;
-    if player_q.iter().count() > 0 {
-        level = player_q.single().map_level;
-    }
+    let level = graph.current;
+    let tileset = tilesets.current();
 
     // load template from file and spawn entities
     let template = Templates::load();
-    template.spawn_entities(&mut commands, atlas, level as usize, &mut mb);
+
+    // the bottom of a side branch keeps its reward far away from the player
+    if graph.down(level).is_none() {
+        if let Some(reward) = plans.branch(level.branch).and_then(|b| b.reward.as_ref()) {
+            let reward_start = mb.amulet_start;
+            template.spawn_named(
+                reward,
+                &reward_start,
+                &mut commands,
+                atlas.atlas.clone(),
+                tileset,
+                &mut mb,
+            );
+        }
+    }
+
+    // boss levels get their unique boss at the prefab marker, or far away from the player
+    if let Some(boss) = &plans.get(level).boss {
+        let boss_start = mb.boss_start.unwrap_or(mb.amulet_start);
+        template.spawn_boss(
+            boss,
+            &boss_start,
+            &mut commands,
+            atlas.atlas.clone(),
+            tileset,
+            &mut mb,
+            &mut run_state,
+        );
+    }
+
+    // the theme hangs its torches on the walls
+    for pos in mb.torch_positions() {
+        spawn_torch(&mut commands, &atlas, tileset, pos);
+    }
+
+    let difficulty = graph.difficulty(level) as usize;
+    template.spawn_entities(&mut commands, atlas, tileset, difficulty, &mut mb);
+}
+
+fn spawn_torch(
+    commands: &mut Commands,
+    atlas: &CharsetAsset,
+    tileset: &Tileset,
+    position: Position,
+) {
+    let tile_sprite = TileSprite::new("torch", Color::ORANGE);
+    commands.spawn((
+        SpriteSheetBundle {
+            texture_atlas: atlas.atlas.clone(),
+            sprite: tileset.sprite(&tile_sprite.id, tile_sprite.color),
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        tile_sprite,
+        Naming("Torch".to_string()),
+        Position {
+            x: position.x,
+            y: position.y,
+            z: 1,
+        },
+        TileSize::square(1.0),
+        LightSource {
+            radius: TORCH_RADIUS,
+            intensity: TORCH_INTENSITY,
+        },
+    ));
 }
 
-pub fn spawn_player(mut commands: Commands, atlas: Res<CharsetAsset>, mut mb: ResMut<MapBuilder>) {
+pub fn spawn_player(
+    mut commands: Commands,
+    atlas: Res<CharsetAsset>,
+    tilesets: Res<Tilesets>,
+    mut mb: ResMut<MapBuilder>,
+) {
     let player_start = mb.player_start;
+    let tile_sprite = TileSprite::new("player", Color::WHITE);
 
     let entity = commands
         .spawn((
             SpriteSheetBundle {
                 texture_atlas: atlas.atlas.clone(),
-                sprite: TextureAtlasSprite {
-                    custom_size: Some(Vec2::new(1.0, 1.0)),
-                    index: '@' as usize,
-                    ..Default::default()
-                },
+                sprite: tilesets
+                    .current()
+                    .sprite(&tile_sprite.id, tile_sprite.color),
                 ..Default::default()
             },
-            Player { map_level: 0 },
+            tile_sprite,
+            Player,
             Naming("Player".to_string()),
             Position {
                 x: player_start.x,
@@ -56,33 +128,28 @@ pub fn spawn_player(mut commands: Commands, atlas: Res<CharsetAsset>, mut mb: Re
 fn spawn_amulet_of_yala(
     mut commands: Commands,
     atlas: Res<CharsetAsset>,
+    tilesets: Res<Tilesets>,
     mb: Res<MapBuilder>,
-    player_q: Query<&Player>,
+    plans: Res<LevelPlans>,
+    transition: Res<LevelTransition>,
 ) {
-    // start by getting the player, if it exists, to get the level
-    // if it doesnt exist, then it is level 0
-    let mut level = 0;
-    if player_q.iter().count() > 0 {
-        level = player_q.single().map_level;
-        // increase level by 1, because this system gets executed before the post_nextlevel
-        level += 1;
-    }
+    // this system gets executed before the post_nextlevel, so look where the player goes
+    let level = transition.destination;
 
-    // only spawn amulet if we are in the last level
-    if level == 2 {
+    // only spawn amulet if the level plan asks for it
+    if plans.get(level).goal == LevelGoal::Amulet {
         let amulet_start = mb.amulet_start;
+        let tile_sprite = TileSprite::new("amulet", Color::GOLD);
         commands.spawn((
             SpriteSheetBundle {
                 texture_atlas: atlas.atlas.clone(),
-                sprite: TextureAtlasSprite {
-                    color: Color::GOLD,
-                    custom_size: Some(Vec2::new(1.0, 1.0)),
-                    index: 6,
-                    ..Default::default()
-                },
+                sprite: tilesets
+                    .current()
+                    .sprite(&tile_sprite.id, tile_sprite.color),
                 visibility: Visibility::Hidden,
                 ..Default::default()
             },
+            tile_sprite,
             Item,
             TileSize::square(1.0),
             Position {
@@ -96,56 +163,141 @@ fn spawn_amulet_of_yala(
     }
 }
 
-// player, enemies and tiles have position
-fn despawn_all_with_position(mut commands: Commands, position_q: Query<Entity, With<Position>>) {
+// a new game starts with no bosses spawned or defeated
+fn reset_run_state(mut commands: Commands) {
+    commands.insert_resource(RunState::default());
+}
+
+// player, enemies and tiles have position, or are stashed in a level left behind
+fn despawn_all_with_position(
+    mut commands: Commands,
+    position_q: Query<Entity, Or<(With<Position>, With<Stashed>)>>,
+) {
     for e in position_q.iter() {
         commands.entity(e).despawn_recursive();
     }
 }
 
-// pre_advance level requires to delete all entities, except the player their items
+// pre_advance level stashes all entities, except the player their items, so the level
+// can be restored when the player comes back.
 // set the field of view to dirty so it is re-calculated
 fn pre_advance_level(
     mut commands: Commands,
-    position_q: Query<Entity, (With<Position>, Without<Player>, Without<Carried>)>,
+    mut position_q: Query<
+        (Entity, &Position, Option<&mut Visibility>),
+        (Without<Player>, Without<Carried>),
+    >,
+    graph: Res<DungeonGraph>,
     mut fov_q: Query<&mut FieldOfView>,
 ) {
-    // remove all the entities with position component except player
-    for e in position_q.iter() {
-        commands.entity(e).despawn_recursive();
+    let level = graph.current;
+
+    // take the position away from all the entities except player, and hide them
+    for (e, pos, visibility) in position_q.iter_mut() {
+        let mut stashed = Stashed {
+            level,
+            position: *pos,
+            visibility: None,
+        };
+        if let Some(mut visibility) = visibility {
+            stashed.visibility = Some(*visibility);
+            *visibility = Visibility::Hidden;
+        }
+        commands.entity(e).remove::<Position>().insert(stashed);
     }
 
     // set all the fov is_dirty to true, so they will need to be recalculated
     fov_q.iter_mut().for_each(|mut fov| fov.is_dirty = true);
 }
 
-// post_advance level sets the location of the player in the new map, advaces its level var
+// post_advance level sets the location of the player in the new map, changes its level var
 fn post_advance_level(
     mut mb: ResMut<MapBuilder>,
-    mut player_q: Query<(Entity, &mut Position, &mut Player)>,
+    mut transition: ResMut<LevelTransition>,
+    mut graph: ResMut<DungeonGraph>,
+    mut player_q: Query<(Entity, &mut Position), With<Player>>,
 ) {
-    // get player position from new map
-    let player_start = mb.player_start;
+    // going down the player arrives at the level start, going up at the stairs it came from
+    let branch_stairs = mb
+        .branch_stairs
+        .iter()
+        .find(|(_, branch)| *branch == transition.origin.branch)
+        .map(|(pos, _)| *pos);
+    let stairs = match (transition.going_up, branch_stairs) {
+        (false, _) => mb.player_start,
+        (true, Some(branch_stairs)) if transition.origin.depth == 0 => branch_stairs,
+        (true, _) => mb.amulet_start,
+    };
+    let arrival = free_tile_near(&mb, stairs);
 
     // get player and set its position based on new map and also update map level
-    let (player_ent, mut player_pos, mut player) = player_q.single_mut();
-    player_pos.x = player_start.x;
-    player_pos.y = player_start.y;
-    player.map_level += 1;
+    let (player_ent, mut player_pos) = player_q.single_mut();
+    player_pos.x = arrival.x;
+    player_pos.y = arrival.y;
+    graph.current = transition.destination;
+    transition.arrival = Some(arrival);
     // also update the map with the occupation info
-    mb.entity_occupy_tile(player_ent, player_start);
+    mb.entity_occupy_tile(player_ent, arrival);
+}
+
+// the target, or the closest walkable tile to it if a monster is standing there
+fn free_tile_near(mb: &MapBuilder, target: Position) -> Position {
+    let is_free = |pos: Position| {
+        mb.map.can_enter_tile(pos) && mb.map.occupation[map_idx(pos.x, pos.y)].is_none()
+    };
+    if is_free(target) {
+        return target;
+    }
+    (0..mb.map.tiles.len())
+        .map(|idx| Position::from(mb.map.index_to_point2d(idx)))
+        .filter(|pos| is_free(*pos))
+        .min_by(|a, b| {
+            let da = DistanceAlg::Pythagoras.distance2d(target.into(), (*a).into());
+            let db = DistanceAlg::Pythagoras.distance2d(target.into(), (*b).into());
+            da.partial_cmp(&db).unwrap()
+        })
+        .unwrap_or(target)
+}
+
+// brings back the entities of a level visited before, as they were left
+fn restore_level(
+    mut commands: Commands,
+    graph: Res<DungeonGraph>,
+    mut stashed_q: Query<(Entity, &Stashed, Option<&mut Visibility>)>,
+) {
+    let level = graph.current;
+    for (e, stashed, visibility) in stashed_q.iter_mut() {
+        if stashed.level != level {
+            continue;
+        }
+        if let (Some(mut visibility), Some(stashed_visibility)) = (visibility, stashed.visibility) {
+            *visibility = stashed_visibility;
+        }
+        commands
+            .entity(e)
+            .insert(stashed.position)
+            .remove::<Stashed>();
+    }
 }
 
 pub struct SpawnerPlugin;
 impl Plugin for SpawnerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnExit(TurnState::StartScreen), (spawn_player, spawn_level))
+        app.init_resource::<RunState>()
+            .add_systems(OnEnter(TurnState::StartScreen), reset_run_state)
+            .add_systems(OnExit(TurnState::StartScreen), (spawn_player, spawn_level))
             .add_systems(OnEnter(TurnState::GameOver), despawn_all_with_position)
             .add_systems(OnEnter(TurnState::Victory), despawn_all_with_position)
             .add_systems(OnEnter(TurnState::NextLevel), pre_advance_level)
             .add_systems(
                 OnExit(TurnState::NextLevel),
-                (spawn_amulet_of_yala, post_advance_level, spawn_level).chain(),
+                (
+                    spawn_amulet_of_yala.run_if(level_is_new),
+                    post_advance_level,
+                    spawn_level.run_if(level_is_new),
+                    restore_level.run_if(level_is_restored),
+                )
+                    .chain(),
             );
     }
 }
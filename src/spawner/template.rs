@@ -9,11 +9,32 @@ pub struct Template {
     pub levels: HashSet<usize>,
     pub frequency: i32,
     pub name: String,
-    pub glyph: char,
+    // logical sprite, found in the tileset in use
+    pub sprite: String,
     pub provides: Option<Vec<(String, i32)>>,
     pub description: Option<String>,
     pub hp: Option<i32>,
     pub base_damage: Option<i32>,
+    // how much of the level spawn budget this entity consumes
+    #[serde(default = "default_cost")]
+    pub cost: i32,
+    // unique entities, like bosses, spawn at most once per run and never from the budget
+    #[serde(default)]
+    pub unique: bool,
+}
+
+fn default_cost() -> i32 {
+    1
+}
+
+// a set of enemies that always spawn together, like a goblin pack
+#[derive(Clone, Deserialize, Debug)]
+pub struct SpawnGroup {
+    pub name: String,
+    pub levels: HashSet<usize>,
+    pub frequency: i32,
+    // names of the templates in the group
+    pub members: Vec<String>,
 }
 
 #[derive(Clone, Deserialize, Debug, PartialEq)]
@@ -25,8 +46,24 @@ pub enum EntityType {
 #[derive(Clone, Deserialize, Debug)]
 pub struct Templates {
     pub entities: Vec<Template>,
+    #[serde(default)]
+    pub groups: Vec<SpawnGroup>,
+}
+
+// something that can be picked when filling the level budget
+#[derive(Clone, Copy)]
+enum Spawnable<'a> {
+    Single(&'a Template),
+    Group(&'a SpawnGroup),
 }
 
+// budget for level 0, and how much it grows with each level
+const BASE_BUDGET: i32 = 30;
+const BUDGET_PER_LEVEL: i32 = 10;
+// percentage chance that a monster is picked from a deeper level
+const OUT_OF_DEPTH_CHANCE: i32 = 5;
+const MAX_OUT_OF_DEPTH: usize = 2;
+
 static TEMPLATE: &'static [u8] = include_bytes!("../../assets/template.ron");
 
 impl Templates {
@@ -38,27 +75,203 @@ impl Templates {
         &self,
         commands: &mut Commands,
         atlas: Res<CharsetAsset>,
+        tileset: &Tileset,
         level: usize,
-        mut mb: &mut ResMut<MapBuilder>,
+        mb: &mut ResMut<MapBuilder>,
+    ) {
+        let mut rng = game_rng();
+        let mut spawn_points = mb.enemies_start.clone();
+        // the region where the player starts is kept free of monsters
+        let start_region = mb.regions.region_at(mb.player_start);
+        let mut budget = BASE_BUDGET + BUDGET_PER_LEVEL * level as i32;
+        // tiles already used by this level spawn
+        let mut taken: HashSet<usize> = HashSet::new();
+
+        // vault markers always get an entity of the right type, paid from the same budget
+        let markers = mb
+            .monster_starts
+            .iter()
+            .map(|pos| (*pos, EntityType::Enemy))
+            .chain(mb.item_starts.iter().map(|pos| (*pos, EntityType::Item)))
+            .collect::<Vec<_>>();
+        for (pos, entity_type) in markers {
+            if let Some(spawnable) =
+                self.pick_spawnable(level, i32::MAX, Some(entity_type), &mut rng)
+            {
+                budget -=
+                    self.spawn_spawnable(spawnable, pos, &mut taken, commands, &atlas, tileset, mb);
+            }
+        }
+
+        while budget > 0 && !spawn_points.is_empty() {
+            // once in a while, pick a monster from deeper levels
+            let mut spawnable = None;
+            if rng.gen_range(0..100) < OUT_OF_DEPTH_CHANCE {
+                let deeper = level + rng.gen_range(1..=MAX_OUT_OF_DEPTH);
+                spawnable = self.pick_spawnable(deeper, budget, Some(EntityType::Enemy), &mut rng);
+            }
+            let spawnable =
+                match spawnable.or_else(|| self.pick_spawnable(level, budget, None, &mut rng)) {
+                    Some(spawnable) => spawnable,
+                    // nothing else fits in the remaining budget
+                    None => break,
+                };
+
+            let pos = spawn_points.remove(rng.gen_range(0..spawn_points.len()));
+            let idx = map_idx(pos.x, pos.y);
+            if taken.contains(&idx) || mb.map.occupation[idx].is_some() {
+                continue;
+            }
+            let is_enemy = match spawnable {
+                Spawnable::Single(template) => template.entity_type == EntityType::Enemy,
+                Spawnable::Group(_) => true,
+            };
+            if is_enemy && start_region.is_some() && mb.regions.region_at(pos) == start_region {
+                continue;
+            }
+
+            budget -=
+                self.spawn_spawnable(spawnable, pos, &mut taken, commands, &atlas, tileset, mb);
+        }
+    }
+
+    // returns the cost of what was spawned, a group only pays for the members that fit
+    fn spawn_spawnable(
+        &self,
+        spawnable: Spawnable,
+        pos: Position,
+        taken: &mut HashSet<usize>,
+        commands: &mut Commands,
+        atlas: &CharsetAsset,
+        tileset: &Tileset,
+        mb: &mut ResMut<MapBuilder>,
+    ) -> i32 {
+        match spawnable {
+            Spawnable::Single(template) => {
+                taken.insert(map_idx(pos.x, pos.y));
+                self.spawn_entity(&pos, template, commands, atlas.atlas.clone(), tileset, mb);
+                template.cost
+            }
+            Spawnable::Group(group) => {
+                let positions = mb.group_positions(pos, group.members.len(), taken);
+                let mut cost = 0;
+                for (member, member_pos) in group.members.iter().zip(positions) {
+                    if let Some(template) = self.find(member) {
+                        taken.insert(map_idx(member_pos.x, member_pos.y));
+                        self.spawn_entity(
+                            &member_pos,
+                            template,
+                            commands,
+                            atlas.atlas.clone(),
+                            tileset,
+                            mb,
+                        );
+                        cost += template.cost;
+                    } else {
+                        println!("Warning: {} has an unknown member {}", group.name, member);
+                    }
+                }
+                cost
+            }
+        }
+    }
+
+    // spawns a unique template as the level boss, unless it was already spawned this run
+    pub fn spawn_boss(
+        &self,
+        name: &str,
+        position: &Position,
+        commands: &mut Commands,
+        atlas: Handle<TextureAtlas>,
+        tileset: &Tileset,
+        mb: &mut ResMut<MapBuilder>,
+        run_state: &mut RunState,
     ) {
-        let mut rng = rand::thread_rng();
-        let spawn_points = mb.enemies_start.clone();
+        if run_state.spawned_uniques.contains(name) {
+            return;
+        }
+
+        if let Some(template) = self.find(name) {
+            let boss = self.spawn_entity(position, template, commands, atlas, tileset, mb);
+            commands.entity(boss).insert(Boss);
+            run_state.spawned_uniques.insert(name.to_string());
+        } else {
+            println!("Warning: there is no template for boss {}", name);
+        }
+    }
 
-        let mut available_entities = Vec::new();
+    // spawns a template by name, like the reward at the bottom of a branch
+    pub fn spawn_named(
+        &self,
+        name: &str,
+        position: &Position,
+        commands: &mut Commands,
+        atlas: Handle<TextureAtlas>,
+        tileset: &Tileset,
+        mb: &mut ResMut<MapBuilder>,
+    ) {
+        match self.find(name) {
+            Some(template) => {
+                self.spawn_entity(position, template, commands, atlas, tileset, mb);
+            }
+            None => println!("Warning: there is no template for {}", name),
+        }
+    }
+
+    fn find(&self, name: &str) -> Option<&Template> {
+        self.entities.iter().find(|t| t.name == name)
+    }
+
+    fn cost(&self, spawnable: Spawnable) -> i32 {
+        match spawnable {
+            Spawnable::Single(template) => template.cost,
+            Spawnable::Group(group) => group
+                .members
+                .iter()
+                .filter_map(|member| self.find(member))
+                .map(|template| template.cost)
+                .sum(),
+        }
+    }
+
+    // weighted pick between all the templates and groups of a level that fit in the budget
+    fn pick_spawnable(
+        &self,
+        level: usize,
+        budget: i32,
+        entity_type: Option<EntityType>,
+        rng: &mut impl Rng,
+    ) -> Option<Spawnable<'_>> {
+        let mut available = Vec::new();
         self.entities
             .iter()
-            .filter(|e| e.levels.contains(&level))
+            .filter(|t| t.levels.contains(&level) && t.cost <= budget && !t.unique)
+            .filter(|t| entity_type.as_ref().map_or(true, |et| t.entity_type == *et))
             .for_each(|t| {
                 for _ in 0..t.frequency {
-                    available_entities.push(t);
+                    available.push(Spawnable::Single(t));
+                }
+            });
+        self.groups
+            .iter()
+            .filter(|g| g.levels.contains(&level))
+            .filter(|_| {
+                entity_type
+                    .as_ref()
+                    .map_or(true, |et| *et == EntityType::Enemy)
+            })
+            .filter(|g| self.cost(Spawnable::Group(g)) <= budget)
+            .for_each(|g| {
+                for _ in 0..g.frequency {
+                    available.push(Spawnable::Group(g));
                 }
             });
 
-        spawn_points.iter().for_each(|pos| {
-            let target_index = rng.gen_range(0..available_entities.len());
-            let entity = available_entities[target_index];
-            self.spawn_entity(pos, entity, commands, atlas.atlas.clone(), &mut mb);
-        });
+        if available.is_empty() {
+            None
+        } else {
+            Some(available[rng.gen_range(0..available.len())])
+        }
     }
 
     fn spawn_entity(
@@ -67,23 +280,22 @@ impl Templates {
         template: &Template,
         commands: &mut Commands,
         atlas: Handle<TextureAtlas>,
+        tileset: &Tileset,
         mb: &mut ResMut<MapBuilder>,
-    ) {
+    ) -> Entity {
+        let color = match template.entity_type {
+            EntityType::Item => Color::GREEN,
+            EntityType::Enemy => Color::rgb(0.698, 0.094, 0.168),
+        };
+        let tile_sprite = TileSprite::new(&template.sprite, color);
         let mut entity = commands.spawn((
             SpriteSheetBundle {
                 texture_atlas: atlas,
-                sprite: TextureAtlasSprite {
-                    custom_size: Some(Vec2::new(1.0, 1.0)),
-                    index: template.glyph as usize,
-                    color: match template.entity_type {
-                        EntityType::Item => Color::GREEN,
-                        EntityType::Enemy => Color::rgb(0.698, 0.094, 0.168),
-                    },
-                    ..Default::default()
-                },
+                sprite: tileset.sprite(&tile_sprite.id, tile_sprite.color),
                 visibility: Visibility::Hidden,
                 ..Default::default()
             },
+            tile_sprite,
             TileSize::square(1.0),
             Naming(template.name.clone().to_string()),
             Position {
@@ -122,6 +334,12 @@ impl Templates {
                     "MagicMap" => {
                         entity.insert(ProvidesDungeonMap);
                     }
+                    "Light" => {
+                        entity.insert(LightSource {
+                            radius: *n,
+                            intensity: 1.0,
+                        });
+                    }
                     _ => {
                         println!("Warning: we don't know how to provide {}", provides);
                     }
@@ -134,5 +352,7 @@ impl Templates {
                 entity.insert(Weapon);
             }
         }
+
+        entity.id()
     }
 }
@@ -0,0 +1,239 @@
+use crate::prelude::*;
+use bevy::app::AppExit;
+use serde::{Deserialize, Serialize};
+
+// what the player wants to do. The keyboard, the menus, replays and bots send these,
+// and resolve_actions is the only one turning them into changes to the game
+#[derive(Event, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PlayerAction {
+    // a step, or an attack if there is an enemy there
+    Move(i32, i32),
+    // steps in a direction until something interesting happens
+    Run(i32, i32),
+    // walks to what is not explored yet, and then to the exit
+    Explore,
+    // walks to a revealed tile, like the mouse clicks ask
+    Travel(i32, i32),
+    Wait,
+    Grab,
+    // the item number in the inventory and equipment lists
+    UseItem(i32),
+    Equip(i32),
+    OpenInventory,
+    OpenEquipment,
+    OpenMap,
+    // examine the tiles around with a cursor
+    Look,
+    CloseMenu,
+    CycleTileset,
+    Quit,
+}
+
+// sent for the action resolve_actions applies, the one replays record
+#[derive(Event, Clone, Copy)]
+pub struct ResolvedAction(pub PlayerAction);
+
+impl PlayerAction {
+    // the actions that change the game, the others only change what is shown. Running,
+    // exploring and traveling are made of moves, and those are what changes the game
+    pub fn changes_game(self) -> bool {
+        matches!(
+            self,
+            PlayerAction::Move(..)
+                | PlayerAction::Wait
+                | PlayerAction::Grab
+                | PlayerAction::UseItem(_)
+                | PlayerAction::Equip(_)
+        )
+    }
+
+    // while a menu is open only what the menu offers can be done, and looking around can
+    // only be stopped
+    fn allowed_in(self, state: TurnState) -> bool {
+        match state {
+            TurnState::AwaitingInput => true,
+            TurnState::InMenus => matches!(
+                self,
+                PlayerAction::UseItem(_)
+                    | PlayerAction::Equip(_)
+                    | PlayerAction::CloseMenu
+                    | PlayerAction::Quit
+            ),
+            TurnState::Looking => matches!(self, PlayerAction::CloseMenu | PlayerAction::Quit),
+            _ => false,
+        }
+    }
+}
+
+// one action per frame, the rest are dropped like extra key presses
+pub fn resolve_actions(
+    mut commands: Commands,
+    mut actions: EventReader<PlayerAction>,
+    mut game_log: ResMut<GameLog>,
+    turn_state: Res<State<TurnState>>,
+    mut next_state: ResMut<NextState<TurnState>>,
+    mut popup_state: ResMut<NextState<PopUpState>>,
+    mut tilesets: ResMut<Tilesets>,
+    mb: Res<MapBuilder>,
+    mut running: ResMut<Running>,
+    (mut resolved, mut exit): (EventWriter<ResolvedAction>, EventWriter<AppExit>),
+    player_q: Query<(Entity, &Position), With<Player>>,
+    enemies_q: Query<(Entity, &Position), With<Enemy>>,
+    // carried items keep their position, they can't be grabbed again
+    items_q: Query<(Entity, &Position, &Naming), (With<Item>, Without<Carried>)>,
+    inventory_q: Query<(Entity, &Carried), Without<Weapon>>,
+    weapons_q: Query<(Entity, &Carried), With<Weapon>>,
+    equipped_q: Query<Entity, With<Equipped>>,
+) {
+    let action = actions.read().next().copied();
+    actions.clear();
+    let action = match action {
+        Some(action) => action,
+        None => return,
+    };
+    if !action.allowed_in(*turn_state.get()) {
+        return;
+    }
+    resolved.send(ResolvedAction(action));
+
+    let (player_ent, pos) = player_q.single();
+    let mut takes_turn = true;
+
+    match action {
+        PlayerAction::Move(dx, dy) => {
+            let destination = Position::new(pos.x + dx, pos.y + dy, pos.z);
+            // if there's an enemy, attack it. Otherwise it is just a movement
+            let enemy = enemies_q.iter().find(|(_, pos)| **pos == destination);
+            // enemies around a wall corner can't be reached either
+            match enemy.filter(|_| mb.map.can_step(*pos, destination)) {
+                Some((victim, _)) => {
+                    commands.spawn(WantsToAttack {
+                        attacker: player_ent,
+                        victim,
+                    });
+                }
+                None => {
+                    commands.spawn(WantsToMove {
+                        entity: player_ent,
+                        destination,
+                    });
+                }
+            }
+        }
+        PlayerAction::Run(dx, dy) => {
+            // the steps are taken one turn at a time by run_player
+            running.start(dx, dy);
+            takes_turn = false;
+        }
+        PlayerAction::Explore => {
+            running.explore();
+            takes_turn = false;
+        }
+        PlayerAction::Travel(x, y) => {
+            running.travel(Position::new_from2d(x, y));
+            takes_turn = false;
+        }
+        PlayerAction::Wait => game_log.add_entry("Player waits.\n".to_string()),
+        PlayerAction::Grab => {
+            // Grab item at this position
+            items_q
+                .iter()
+                .filter(|(_, item_pos, _)| **item_pos == *pos)
+                .for_each(|(item_ent, _, name)| {
+                    // remove render info and add carried component
+                    commands
+                        .entity(item_ent)
+                        .remove::<SpriteSheetBundle>()
+                        .insert(Carried(player_ent));
+                    let message = format!("{} grabbed.\n", name.0);
+                    game_log.add_entry(message);
+                });
+        }
+        PlayerAction::UseItem(index) => {
+            // the item at that place of the inventory list, if there is one
+            let item = inventory_q
+                .iter()
+                .filter(|(_, carried)| carried.0 == player_ent)
+                .nth(index as usize)
+                .map(|(item, _)| item);
+            match item {
+                Some(item) => {
+                    commands.spawn(ActivateItem {
+                        used_by: player_ent,
+                        item,
+                    });
+                    popup_state.set(PopUpState::None);
+                }
+                None => takes_turn = false,
+            }
+        }
+        PlayerAction::Equip(index) => {
+            let weapon = weapons_q
+                .iter()
+                .filter(|(_, carried)| carried.0 == player_ent)
+                .nth(index as usize)
+                .map(|(weapon, _)| weapon);
+            match weapon {
+                Some(weapon) => {
+                    // remove equipped component from every weapon equipped (should be only 1)
+                    for equipped in equipped_q.iter() {
+                        commands.entity(equipped).remove::<Equipped>();
+                    }
+                    commands.entity(weapon).insert(Equipped);
+                    popup_state.set(PopUpState::None);
+                }
+                None => takes_turn = false,
+            }
+        }
+        PlayerAction::OpenInventory | PlayerAction::OpenEquipment | PlayerAction::OpenMap => {
+            popup_state.set(match action {
+                PlayerAction::OpenInventory => PopUpState::InventoryPopup,
+                PlayerAction::OpenEquipment => PopUpState::EquipmentPopup,
+                _ => PopUpState::LevelMap,
+            });
+            next_state.set(TurnState::InMenus);
+            takes_turn = false;
+        }
+        PlayerAction::Look => {
+            next_state.set(TurnState::Looking);
+            takes_turn = false;
+        }
+        PlayerAction::CloseMenu => {
+            popup_state.set(PopUpState::None);
+            next_state.set(TurnState::AwaitingInput);
+            takes_turn = false;
+        }
+        PlayerAction::CycleTileset => {
+            // switch to the next tileset, it doesn't take a turn
+            tilesets.next();
+            let message = format!("Tileset: {}.\n", tilesets.current().name);
+            game_log.add_entry(message);
+            takes_turn = false;
+        }
+        PlayerAction::Quit => {
+            exit.send(AppExit);
+            takes_turn = false;
+        }
+    }
+
+    if takes_turn {
+        next_state.set(TurnState::PlayerTurn);
+    }
+}
+
+pub struct ActionsPlugin;
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayerAction>()
+            .add_event::<ResolvedAction>()
+            // actions come from the keyboard or the menus, but also from replays and bots
+            .add_systems(
+                Update,
+                resolve_actions.run_if(
+                    in_state(TurnState::AwaitingInput)
+                        .or_else(in_state(TurnState::InMenus))
+                        .or_else(in_state(TurnState::Looking)),
+                ),
+            );
+    }
+}
@@ -25,8 +25,9 @@ pub fn chasing(
 
         let idx = map_idx(pos.x, pos.y);
         if let Some(destination) = DijkstraMap::find_lowest_exit(&dijkstra_map, idx, &mb.map) {
+            // next to the player, diagonals included, and not around a wall corner
             let distance = DistanceAlg::Pythagoras.distance2d((*pos).into(), (*player_pos).into());
-            let destination: Position = if distance > 1.2 {
+            let destination: Position = if distance > 1.5 || !map.can_step(*pos, *player_pos) {
                 Position::from((map.index_to_point2d(destination), pos.z))
             } else {
                 *player_pos
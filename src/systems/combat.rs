@@ -4,10 +4,14 @@ pub fn combat(
     mut commands: Commands,
     mut mb: ResMut<MapBuilder>,
     mut gamelog: ResMut<GameLog>,
+    mut run_state: ResMut<RunState>,
     attacker_messages: Query<(Entity, &WantsToAttack)>,
     player: Query<Entity, With<Player>>,
+    boss_query: Query<&Boss>,
     names_query: Query<&Naming>,
     mut health_query: Query<(&mut Health, &Position, &Naming)>,
+    positions_query: Query<&Position>,
+    mut attacks: EventWriter<AttackEvent>,
     damage_query: Query<(&Damage, Option<&Carried>, Option<&Equipped>)>,
 ) {
     // get the list of victim messages
@@ -46,14 +50,30 @@ pub fn combat(
             );
             gamelog.add_entry(message);
 
+            let mut attack = AttackEvent {
+                attacker: *attacker,
+                victim: *victim,
+                from: positions_query.get(*attacker).copied().unwrap_or(*pos),
+                to: *pos,
+                damage: final_damage,
+                killed: false,
+            };
+
             // less than 1 HP remove it
             if hp.current < 1 {
                 if let Ok(_) = player.get(*victim) {
                 } else {
+                    attack.killed = true;
+                    // remember defeated bosses, some levels are won by killing them
+                    if boss_query.get(*victim).is_ok() {
+                        run_state.defeated_uniques.insert(name.0.clone());
+                        gamelog.add_entry(format!("{} is defeated!\n", name.0));
+                    }
                     mb.free_occupy_tile(*pos);
                     commands.entity(*victim).despawn();
                 }
             }
+            attacks.send(attack);
         }
         // remove the message
         commands.entity(*message).despawn();
@@ -3,9 +3,15 @@ use crate::prelude::*;
 pub fn end_turn(
     turn_state: ResMut<State<TurnState>>,
     mut next_state: ResMut<NextState<TurnState>>,
+    plans: Res<LevelPlans>,
+    run_state: Res<RunState>,
+    mut transition: ResMut<LevelTransition>,
+    graph: Res<DungeonGraph>,
     player_hp_q: Query<(&Health, &Position), With<Player>>,
     amulet_q: Query<&Position, With<AmuletOfYala>>,
     exit_q: Query<&Position, With<ExitTile>>,
+    up_stairs_q: Query<&Position, With<UpStairsTile>>,
+    branch_stairs_q: Query<(&Position, &BranchStairsTile)>,
 ) {
     let (player_hp, player_pos) = player_hp_q.single();
     //let current_state: TurnState = *turn_state.get().clone();
@@ -37,8 +43,43 @@ pub fn end_turn(
         new_state = TurnState::Victory;
     }
 
-    if player_pos == exit_pos {
-        new_state = TurnState::NextLevel;
+    // on boss levels, killing the boss wins the game
+    let plan = plans.get(graph.current);
+    if plan.goal == LevelGoal::KillBoss {
+        if let Some(boss) = &plan.boss {
+            if run_state.defeated_uniques.contains(boss) {
+                new_state = TurnState::Victory;
+            }
+        }
+    }
+
+    // stairs where the player just arrived don't count until the player steps off them
+    if transition
+        .arrival
+        .map_or(false, |arrival| arrival != *player_pos)
+    {
+        transition.arrival = None;
+    }
+    if transition.arrival.is_none() {
+        let branch = branch_stairs_q
+            .iter()
+            .find(|(pos, _)| *pos == player_pos)
+            .map(|(_, stairs)| stairs.branch);
+
+        let destination = if player_pos == exit_pos {
+            graph.down(graph.current).map(|level| (level, false))
+        } else if up_stairs_q.iter().any(|pos| pos == player_pos) {
+            graph.up(graph.current).map(|level| (level, true))
+        } else {
+            branch.map(|branch| (LevelId::new(branch, 0), false))
+        };
+
+        if let Some((destination, going_up)) = destination {
+            transition.origin = graph.current;
+            transition.destination = destination;
+            transition.going_up = going_up;
+            new_state = TurnState::NextLevel;
+        }
     }
 
     // change state to new turn
@@ -1,11 +1,30 @@
 use crate::prelude::*;
 
-pub fn fov(mb: Res<MapBuilder>, mut views_query: Query<(&Position, &mut FieldOfView)>) {
-    views_query
-        .iter_mut()
-        .filter(|(_, fov)| fov.is_dirty)
-        .for_each(|(pos, mut fov)| {
-            fov.visible_tiles = field_of_view_set((*pos).into(), fov.radius, &mb.map);
-            fov.is_dirty = false;
-        });
+pub fn fov(
+    mut mb: ResMut<MapBuilder>,
+    light_map: Res<LightMap>,
+    mut views_query: Query<(&Position, &mut FieldOfView, Option<&Player>)>,
+) {
+    for (pos, mut fov, player) in views_query.iter_mut().filter(|(_, fov, _)| fov.is_dirty) {
+        let origin: Point = (*pos).into();
+        fov.visible_tiles = field_of_view_set(origin, fov.radius, &mb.map);
+        fov.is_dirty = false;
+
+        if player.is_some() {
+            // in the dark the player only makes out what is right next to it
+            fov.visible_tiles.retain(|pt| {
+                light_map.is_lit(*pt) || DistanceAlg::Chebyshev.distance2d(origin, *pt) <= 1.0
+            });
+
+            // the map remembers what the player has seen
+            for pt in fov.visible_tiles.iter() {
+                // only touch the map for new tiles, so it is not flagged as changed for nothing
+                if let Some(idx) = mb.map.try_idx((*pt).into()) {
+                    if !mb.map.revealed[idx] {
+                        mb.map.revealed[idx] = true;
+                    }
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,57 @@
+use crate::prelude::*;
+
+// builds the light map from the theme ambient light and every light source around
+pub fn lighting(
+    mb: Res<MapBuilder>,
+    mut light_map: ResMut<LightMap>,
+    lights_q: Query<(&LightSource, Option<&Position>, Option<&Carried>)>,
+    positions_q: Query<&Position>,
+    changed_q: Query<(), (With<LightSource>, Or<(Changed<Position>, Added<Carried>)>)>,
+    moved_q: Query<(), Changed<Position>>,
+    mut player_fov_q: Query<&mut FieldOfView, With<Player>>,
+) {
+    // the map changes as lights or whoever carries them move around, and on a new level
+    let carrier_moved = lights_q
+        .iter()
+        .filter_map(|(_, _, carried)| carried)
+        .any(|carried| moved_q.contains(carried.0));
+    if !mb.is_changed() && changed_q.is_empty() && !carrier_moved {
+        return;
+    }
+
+    let ambient = mb.theme.ambient_light();
+    let mut levels = vec![ambient; mb.map.tiles.len()];
+
+    for (light, pos, carried) in lights_q.iter() {
+        // carried lights shine from whoever carries them, they keep the position where
+        // they were picked up
+        let source = match (carried, pos) {
+            (Some(carried), _) => match positions_q.get(carried.0) {
+                Ok(pos) => *pos,
+                Err(_) => continue,
+            },
+            (None, Some(pos)) => *pos,
+            // stashed in another level
+            (None, None) => continue,
+        };
+
+        let origin: Point = source.into();
+        for pt in field_of_view_set(origin, light.radius, &mb.map) {
+            if let Some(idx) = mb.map.try_idx(pt.into()) {
+                let distance = DistanceAlg::Pythagoras.distance2d(origin, pt);
+                let falloff = 1.0 - distance / (light.radius + 1) as f32;
+                levels[idx] += light.intensity * f32::max(falloff, 0.0);
+            }
+        }
+    }
+
+    levels
+        .iter_mut()
+        .for_each(|level| *level = f32::min(*level, 1.0));
+    light_map.levels = levels;
+
+    // what the player sees depends on the light
+    player_fov_q
+        .iter_mut()
+        .for_each(|mut fov| fov.is_dirty = true);
+}
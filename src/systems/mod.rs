@@ -1,5 +1,6 @@
 use crate::prelude::*;
 
+mod actions;
 mod camera;
 mod combat;
 mod player_input;
@@ -7,10 +8,15 @@ mod player_input;
 mod chasing;
 mod end_turn;
 mod fov;
+mod lighting;
 mod movement;
+mod running;
 mod update_entities_visibility;
 mod use_items;
 
+pub use actions::{PlayerAction, ResolvedAction};
+pub use running::{travel_path, Running};
+
 struct AwaitingInputPlugin;
 impl Plugin for AwaitingInputPlugin {
     fn build(&self, app: &mut App) {
@@ -18,8 +24,9 @@ impl Plugin for AwaitingInputPlugin {
             .add_systems(
                 Update,
                 (
-                    fov::fov,
+                    (lighting::lighting, fov::fov).chain(),
                     update_entities_visibility::update_entities_visibility,
+                    update_tilemap,
                     camera::camera_move,
                 )
                     .run_if(in_state(TurnState::AwaitingInput)),
@@ -36,8 +43,10 @@ impl Plugin for PlayerPlugin {
                 use_items::use_items,
                 combat::combat,
                 movement::movement,
+                lighting::lighting,
                 fov::fov,
                 update_entities_visibility::update_entities_visibility,
+                update_tilemap,
                 camera::camera_move,
                 end_turn::end_turn,
             )
@@ -56,11 +65,14 @@ impl Plugin for MonsterPlugin {
                 chasing::chasing,
                 combat::combat,
                 movement::movement,
+                lighting::lighting,
                 fov::fov,
                 end_turn::end_turn,
             )
                 .chain()
-                .run_if(in_state(TurnState::MonsterTurn)),
+                .run_if(in_state(TurnState::MonsterTurn))
+                // monsters wait for the player move or attack to play
+                .run_if(animations_settled),
         );
     }
 }
@@ -68,7 +80,10 @@ impl Plugin for MonsterPlugin {
 pub struct SystemsPlugin;
 impl Plugin for SystemsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(AwaitingInputPlugin)
+        app.init_resource::<LightMap>()
+            .add_plugins(actions::ActionsPlugin)
+            .add_plugins(running::RunningPlugin)
+            .add_plugins(AwaitingInputPlugin)
             .add_plugins(PlayerPlugin)
             .add_plugins(MonsterPlugin);
     }
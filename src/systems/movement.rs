@@ -8,12 +8,12 @@ pub fn movement(
 ) {
     // for every message to move
     for (message_ent, move_signal) in move_messages.iter() {
-        // if the movement is physically valid
-        if mb.map.can_enter_tile(move_signal.destination) {
-            // if no other character is in that cell
-            if mb.map.is_tile_occupied(move_signal.destination) {
-                // get the entity and its alive status
-                if let Ok((mov_ent, mut position, mut fov)) = movers.get_mut(move_signal.entity) {
+        // get the entity and its alive status
+        if let Ok((mov_ent, mut position, mut fov)) = movers.get_mut(move_signal.entity) {
+            // if the movement is physically valid, also around corners
+            if mb.map.can_step(*position, move_signal.destination) {
+                // if no other character is in that cell
+                if mb.map.is_tile_occupied(move_signal.destination) {
                     // update occupation map
                     mb.move_entity_occupation(mov_ent, *position, move_signal.destination);
                     // and execute the movement
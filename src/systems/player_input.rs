@@ -1,101 +1,87 @@
+use super::actions::resolve_actions;
 use crate::prelude::*;
-use bevy::app::AppExit;
 
-pub fn player_input(
-    mut commands: Commands,
-    mut keyboard_input: ResMut<Input<KeyCode>>,
-    mut game_log: ResMut<GameLog>,
-    player_position: Query<(Entity, &Position), With<Player>>,
-    enemies: Query<(Entity, &Position), With<Enemy>>,
-    items: Query<(Entity, &Position, &Naming), With<Item>>,
-    mut next_state: ResMut<NextState<TurnState>>,
-    mut popup_state: ResMut<NextState<PopUpState>>,
-    mut exit: EventWriter<AppExit>,
-) {
-    let (player_ent, pos) = player_position.single();
-    let mut action = true;
-    let mut wait = false;
+// the move key being held, and when it sends its move again
+#[derive(Resource, Default)]
+pub struct KeyRepeat(Option<(KeyCode, PlayerAction, f32)>);
 
-    let mut new_position = pos.clone();
+// the last bound key pressed while the turn plays out, and its action. It is sent when the
+// player can act again, so typing fast doesn't lose moves
+#[derive(Resource, Default)]
+pub struct BufferedKey(Option<(KeyCode, PlayerAction)>);
 
-    let key = keyboard_input.get_pressed().next().cloned();
-
-    if let Some(key) = key {
-        // print!("{:?}", key);
+impl BufferedKey {
+    pub fn is_some(&self) -> bool {
+        self.0.is_some()
+    }
+}
 
-        match key {
-            KeyCode::Left => new_position.x -= 1,
-            KeyCode::Right => new_position.x += 1,
-            KeyCode::Down => new_position.y -= 1,
-            KeyCode::Up => new_position.y += 1,
-            KeyCode::G => {
-                // Grab item at this position
-                items
-                    .iter()
-                    .filter(|(_, item_pos, _)| **item_pos == *pos)
-                    .for_each(|(item_ent, _, name)| {
-                        // remove render info and add carried component
-                        commands
-                            .entity(item_ent)
-                            .remove::<SpriteSheetBundle>()
-                            .insert(Carried(player_ent));
-                        let message = format!("{} grabbed.\n", name.0);
-                        game_log.add_entry(message);
-                    });
-            }
-            KeyCode::I => {
-                popup_state.set(PopUpState::InventoryPopup);
-                next_state.set(TurnState::InMenus);
-                action = false;
-            }
-            KeyCode::E => {
-                popup_state.set(PopUpState::EquipmentPopup);
-                next_state.set(TurnState::InMenus);
-                action = false;
-            }
-            KeyCode::Escape => {
-                exit.send(AppExit);
-            }
-            _ => wait = true,
+// the bound key pressed this frame and its action. With the run keys held down, moves
+// become runs that way
+fn pressed_action(
+    keyboard_input: &Input<KeyCode>,
+    bindings: &KeyBindings,
+) -> Option<(KeyCode, PlayerAction)> {
+    let (key, action) = keyboard_input
+        .get_just_pressed()
+        .find_map(|key| bindings.action(*key).map(|action| (*key, action)))?;
+    match action {
+        PlayerAction::Move(dx, dy) if keyboard_input.any_pressed(bindings.run.iter().copied()) => {
+            Some((key, PlayerAction::Run(dx, dy)))
         }
+        _ => Some((key, action)),
+    }
+}
 
-        // move to new position
-        if new_position != *pos {
-            // placeholder to know if it just a move or an attack
-            let mut hit_something = false;
-            // check if there is an enemy at the destination position
-            enemies
-                .iter()
-                .filter(|(_, pos)| **pos == new_position)
-                // if there's an enemy, say you hit something and send a WantsToAttack
-                .for_each(|(victim, _)| {
-                    hit_something = true;
+// keeps the keys pressed while the player and the monsters play their turn
+fn buffer_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut buffered: ResMut<BufferedKey>,
+) {
+    if let Some(pressed) = pressed_action(&keyboard_input, &bindings) {
+        buffered.0 = Some(pressed);
+    }
+}
 
-                    commands.spawn(WantsToAttack {
-                        attacker: player_ent,
-                        victim,
-                    });
-                });
+// a new game doesn't get keys pressed in the last one
+fn clear_buffered_key(mut buffered: ResMut<BufferedKey>) {
+    buffered.0 = None;
+}
+
+// the keyboard backend, turns the key pressed into its action. Keys without one do nothing.
+// Move keys held down repeat on the game clock, so it doesn't depend on the frame rate
+pub fn player_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time>,
+    mut repeat: ResMut<KeyRepeat>,
+    mut buffered: ResMut<BufferedKey>,
+    mut actions: EventWriter<PlayerAction>,
+) {
+    let now = time.elapsed_seconds();
+    let buffered = buffered.0.take();
+    let pressed = pressed_action(&keyboard_input, &bindings).or(buffered);
 
-            // if it did not hit then it is just a movement
-            if !hit_something {
-                commands.spawn(WantsToMove {
-                    entity: player_ent,
-                    destination: new_position,
-                });
+    if let Some((key, action)) = pressed {
+        repeat.0 = None;
+        match action {
+            PlayerAction::Move(..) => {
+                actions.send(action);
+                repeat.0 = Some((key, action, now + bindings.repeat_delay));
             }
+            _ => actions.send(action),
         }
-        // else means the user clicked an action which did not move the player.
-        else if wait {
-            game_log.add_entry("Player waits.\n".to_string());
-        }
+        return;
+    }
 
-        if action {
-            // update state
-            next_state.set(TurnState::PlayerTurn);
+    if let Some((key, action, next)) = repeat.0.as_mut() {
+        if !keyboard_input.pressed(*key) {
+            repeat.0 = None;
+        } else if now >= *next {
+            actions.send(*action);
+            *next = now + bindings.repeat_interval;
         }
-
-        keyboard_input.reset_all();
     }
 }
 
@@ -127,12 +113,24 @@ pub fn equip_weapon_log(
 pub struct PlayerInputPlugin;
 impl Plugin for PlayerInputPlugin {
     fn build(&self, app: &mut App) {
-        app
+        app.init_resource::<KeyRepeat>()
+            .init_resource::<BufferedKey>()
             // listening to user input on inventory screen
             .add_systems(
                 Update,
-                (player_input, equip_first_weapon, equip_weapon_log)
+                (
+                    player_input.before(resolve_actions),
+                    equip_first_weapon,
+                    equip_weapon_log,
+                )
                     .run_if(in_state(TurnState::AwaitingInput)),
-            );
+            )
+            .add_systems(
+                Update,
+                buffer_input.run_if(
+                    in_state(TurnState::PlayerTurn).or_else(in_state(TurnState::MonsterTurn)),
+                ),
+            )
+            .add_systems(OnEnter(TurnState::StartScreen), clear_buffered_key);
     }
 }
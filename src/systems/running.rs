@@ -0,0 +1,296 @@
+use super::actions::resolve_actions;
+use super::player_input::{player_input, BufferedKey};
+use crate::prelude::*;
+
+// where a run takes the player
+#[derive(Clone, Copy, PartialEq)]
+enum Heading {
+    // straight in a direction
+    Direction(i32, i32),
+    // to the closest tile not explored yet
+    Unexplored,
+    // to the exit of the level
+    Exit,
+    // along the path to a tile
+    Travel(Position),
+}
+
+struct Run {
+    heading: Heading,
+    // where the last step should have taken the player, none before the first one
+    expected: Option<Position>,
+    // enemies and items already in view
+    seen: Vec<Entity>,
+    health: i32,
+    // the tiles around the player that can be entered
+    openings: usize,
+}
+
+// the run the player is doing, if any
+#[derive(Resource, Default)]
+pub struct Running(Option<Run>);
+
+impl Running {
+    fn start_heading(&mut self, heading: Heading) {
+        self.0 = Some(Run {
+            heading,
+            expected: None,
+            seen: Vec::new(),
+            health: 0,
+            openings: 0,
+        });
+    }
+
+    pub fn start(&mut self, dx: i32, dy: i32) {
+        self.start_heading(Heading::Direction(dx, dy));
+    }
+
+    // explores the level, or goes to the exit when there is nothing left to explore
+    pub fn explore(&mut self) {
+        self.start_heading(Heading::Unexplored);
+    }
+
+    // walks to a tile the player has seen
+    pub fn travel(&mut self, destination: Position) {
+        self.start_heading(Heading::Travel(destination));
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+fn openings(map: &Map, pos: Position) -> usize {
+    (-1..=1)
+        .flat_map(|dx| (-1..=1).map(move |dy| (dx, dy)))
+        .filter(|(dx, dy)| {
+            (*dx, *dy) != (0, 0) && map.can_enter_tile(Position::new_from2d(pos.x + dx, pos.y + dy))
+        })
+        .count()
+}
+
+// stepping on stairs changes level
+fn is_stairs(tile: TileType) -> bool {
+    matches!(
+        tile,
+        TileType::Exit | TileType::UpStairs | TileType::BranchStairs
+    )
+}
+
+// the map as the player knows it, for paths going only through revealed tiles. Stairs
+// are avoided unless the path ends there
+struct KnownMap<'a> {
+    map: &'a Map,
+    destination: usize,
+}
+
+impl BaseMap for KnownMap<'_> {
+    fn get_available_exits(&self, idx: usize) -> SmallVec<[(usize, f32); 10]> {
+        self.map
+            .get_available_exits(idx)
+            .into_iter()
+            .filter(|(exit, _)| {
+                self.map.revealed[*exit]
+                    && (*exit == self.destination || !is_stairs(self.map.tiles[*exit]))
+            })
+            .collect()
+    }
+
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        self.map.get_pathing_distance(idx1, idx2)
+    }
+}
+
+// the tiles from next to the player to a revealed tile, if it can be reached
+pub fn travel_path(map: &Map, from: Position, to: Position) -> Option<Vec<Position>> {
+    let (start, destination) = (map.try_idx(from)?, map.try_idx(to)?);
+    if start == destination || !map.revealed[destination] || !map.can_enter_tile(to) {
+        return None;
+    }
+    let path = a_star_search(start, destination, &KnownMap { map, destination });
+    if !path.success {
+        return None;
+    }
+    Some(
+        path.steps
+            .into_iter()
+            .skip(1)
+            .map(|idx| map.index_to_point2d(idx).into())
+            .collect(),
+    )
+}
+
+// the step getting closer to the targets, if they can be reached. Stairs are only taken
+// when going to them, not to change level on the way
+fn step_towards(map: &Map, pos: Position, targets: &[usize]) -> Option<(i32, i32)> {
+    if targets.is_empty() {
+        return None;
+    }
+    let dijkstra_map = DijkstraMap::new(SCREEN_WIDTH, SCREEN_HEIGHT, targets, map, 1024.0);
+    // the dijkstra map doesn't set its starting tiles to zero
+    let distance = |pos: Position| {
+        map.try_idx(pos).map_or(f32::MAX, |idx| {
+            if targets.contains(&idx) {
+                0.0
+            } else {
+                dijkstra_map.map[idx]
+            }
+        })
+    };
+
+    (-1..=1)
+        .flat_map(|dx| (-1..=1).map(move |dy| (dx, dy)))
+        .map(|(dx, dy)| ((dx, dy), Position::new_from2d(pos.x + dx, pos.y + dy)))
+        .filter(|(_, next)| {
+            map.can_step(pos, *next)
+                && distance(*next) < distance(pos)
+                && (distance(*next) == 0.0 || !is_stairs(map.tiles[map_idx(next.x, next.y)]))
+        })
+        .min_by(|a, b| distance(a.1).partial_cmp(&distance(b.1)).unwrap())
+        .map(|(step, _)| step)
+}
+
+// keeps stepping where the run goes, and stops when a key or button is pressed, the player gets
+// hurt or can't go on, or an enemy or item shows up in view. Running straight also stops
+// with an item underfoot, or more ways to go like a corridor branching or opening into a room
+fn run_player(
+    mut running: ResMut<Running>,
+    keyboard_input: Res<Input<KeyCode>>,
+    buffered: Res<BufferedKey>,
+    mouse_input: Res<Input<MouseButton>>,
+    mb: Res<MapBuilder>,
+    bindings: Res<KeyBindings>,
+    mut game_log: ResMut<GameLog>,
+    mut actions: EventWriter<PlayerAction>,
+    player_q: Query<(&Position, &FieldOfView, &Health), With<Player>>,
+    enemies_q: Query<(Entity, &Position), With<Enemy>>,
+    items_q: Query<(Entity, &Position), (With<Item>, Without<Carried>)>,
+    exits_q: Query<&Position, With<ExitTile>>,
+) {
+    let run = match running.0.as_mut() {
+        Some(run) => run,
+        None => return,
+    };
+    let (pos, fov, health) = player_q.single();
+    let in_view: Vec<Entity> = enemies_q
+        .iter()
+        .chain(items_q.iter())
+        .filter(|(_, pos)| fov.visible_tiles.contains(&(**pos).into()))
+        .map(|(entity, _)| entity)
+        .collect();
+    let openings = openings(&mb.map, *pos);
+    let straight = matches!(run.heading, Heading::Direction(..));
+
+    let stop = keyboard_input.get_just_pressed().next().is_some()
+        || buffered.is_some()
+        || mouse_input.get_just_pressed().next().is_some()
+        || match run.expected {
+            None => false,
+            Some(expected) => {
+                *pos != expected
+                    || health.current < run.health
+                    || in_view.iter().any(|entity| !run.seen.contains(entity))
+                    || (straight && items_q.iter().any(|(_, item_pos)| item_pos == pos))
+                    || (straight && openings > run.openings)
+            }
+        };
+    if stop {
+        running.0 = None;
+        return;
+    }
+
+    // the tiles not explored yet that can be entered, the closest one is where to go
+    let player_idx = map_idx(pos.x, pos.y);
+    let unexplored = || -> Vec<usize> {
+        (0..mb.map.tiles.len())
+            .filter(|idx| {
+                *idx != player_idx
+                    && !mb.map.revealed[*idx]
+                    && !is_stairs(mb.map.tiles[*idx])
+                    && mb.map.can_enter_tile(mb.map.index_to_point2d(*idx))
+            })
+            .collect()
+    };
+    let exits = || -> Vec<usize> {
+        exits_q
+            .iter()
+            .filter_map(|exit| mb.map.try_idx(*exit))
+            .collect()
+    };
+
+    let step = match run.heading {
+        Heading::Direction(dx, dy) => Some((dx, dy)),
+        Heading::Unexplored => match step_towards(&mb.map, *pos, &unexplored()) {
+            Some(step) => Some(step),
+            // exploring with everything explored goes to the exit
+            None if run.expected.is_none() => {
+                run.heading = Heading::Exit;
+                let step = step_towards(&mb.map, *pos, &exits());
+                if step.is_none() {
+                    game_log.add_entry("Nothing left to explore.\n".to_string());
+                }
+                step
+            }
+            None => {
+                let keys = key_names(bindings.keys(PlayerAction::Explore), " or ");
+                let message = if exits().is_empty() {
+                    "Level explored.\n".to_string()
+                } else {
+                    format!("Level explored, press {} to go to the exit.\n", keys)
+                };
+                game_log.add_entry(message);
+                None
+            }
+        },
+        Heading::Exit => step_towards(&mb.map, *pos, &exits()),
+        // the path is found again every step, in case the way got blocked
+        Heading::Travel(destination) => travel_path(&mb.map, *pos, destination)
+            .and_then(|path| path.first().copied())
+            .map(|next| (next.x - pos.x, next.y - pos.y)),
+    };
+
+    // never attack or bump into something while running
+    let step_to = |(dx, dy): (i32, i32)| Position::new(pos.x + dx, pos.y + dy, pos.z);
+    let step = step.filter(|step| {
+        let destination = step_to(*step);
+        mb.map.can_step(*pos, destination)
+            && mb.map.is_tile_occupied(destination)
+            && !enemies_q
+                .iter()
+                .any(|(_, enemy_pos)| *enemy_pos == destination)
+    });
+    let (dx, dy) = match step {
+        Some(step) => step,
+        None => {
+            running.0 = None;
+            return;
+        }
+    };
+
+    run.expected = Some(step_to((dx, dy)));
+    run.seen = in_view;
+    run.health = health.current;
+    run.openings = openings;
+    actions.send(PlayerAction::Move(dx, dy));
+}
+
+// a run stops when the player leaves the level, or the game ends
+fn stop_running(mut running: ResMut<Running>) {
+    running.0 = None;
+}
+
+pub struct RunningPlugin;
+impl Plugin for RunningPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Running>()
+            .add_systems(
+                Update,
+                run_player
+                    .before(player_input)
+                    .before(resolve_actions)
+                    .run_if(in_state(TurnState::AwaitingInput)),
+            )
+            .add_systems(OnEnter(TurnState::NextLevel), stop_running)
+            .add_systems(OnEnter(TurnState::StartScreen), stop_running);
+    }
+}
@@ -2,67 +2,60 @@ use crate::prelude::*;
 
 pub fn update_entities_visibility(
     mut gamelog: ResMut<GameLog>,
+    bindings: Res<KeyBindings>,
+    light_map: Res<LightMap>,
     player_fov_q: Query<&FieldOfView, With<Player>>,
-    mut entities_q: Query<(
-        Entity,
-        &Position,
-        &mut Visibility,
-        Option<&MapTile>,
-        Option<&mut Sprite>,
-        Option<&mut TextureAtlasSprite>,
-    )>,
+    // map tiles are drawn by the tilemap, which takes care of their visibility, and path
+    // marks and the look cursor are always shown
+    mut entities_q: Query<
+        (
+            Entity,
+            &Position,
+            &mut Visibility,
+            Option<&mut TextureAtlasSprite>,
+        ),
+        (
+            Without<MapTile>,
+            Without<Player>,
+            Without<PathStep>,
+            Without<LookCursor>,
+        ),
+    >,
     names_enemies_q: Query<&Naming, With<Enemy>>,
     names_items_q: Query<&Naming, With<Item>>,
 ) {
     // get the player fov
     let player_fov = player_fov_q.single();
 
-    // for every entity with position
-    for (ent, pos, mut vis, map_tile, sprite, atlas_sprite) in entities_q.iter_mut() {
-        // first check if it is a map tile or some other entity. If it is a map tile...
-        if let Some(_) = map_tile {
-            if player_fov.visible_tiles.contains(&((*pos).into())) {
-                // make it visible
-                *vis = Visibility::Visible;
-                // increase the color alpha to 1, to both sprites or atlas_sprite
-                if let Some(mut sprite) = sprite {
-                    sprite.color.set_a(1.0);
-                }
-                if let Some(mut atlas_sprite) = atlas_sprite {
-                    atlas_sprite.color.set_a(1.0);
+    // for every character or entity with position, the player is always visible
+    for (ent, pos, mut vis, sprite) in entities_q.iter_mut() {
+        // if this thing is on the player fov, make it visible
+        if player_fov.visible_tiles.contains(&((*pos).into())) {
+            // tinted by the light around it
+            if let Some(mut sprite) = sprite {
+                let alpha = light_map.alpha_at((*pos).into());
+                if sprite.color.a() != alpha {
+                    sprite.color.set_a(alpha);
                 }
-            } else if *vis == Visibility::Visible {
-                // if visible true but not in fov, tint
-                // decrease the color alpha, to both sprites or atlas_sprite
-                if let Some(mut sprite) = sprite {
-                    sprite.color.set_a(0.1);
+            }
+            // if it was not visible before, make it appear and describe in gamelog
+            if *vis == Visibility::Hidden {
+                *vis = Visibility::Visible;
+                // if enemy, get name update gamelog
+                if let Ok(name) = names_enemies_q.get(ent) {
+                    let message = format!("{} appears.\n", name.0);
+                    gamelog.add_entry(message);
                 }
-                if let Some(mut atlas_sprite) = atlas_sprite {
-                    atlas_sprite.color.set_a(0.1);
+                // if item, provide hint
+                if let Ok(name) = names_items_q.get(ent) {
+                    let keys = key_names(bindings.keys(PlayerAction::Grab), " or ");
+                    let message = format!("{}. Press {} to grab.\n", name.0, keys);
+                    gamelog.add_entry(message);
                 }
             }
         } else {
-            // if it is not a map tile, but some character or entity
-            // if this thing is on the player fov, make it visible
-            if player_fov.visible_tiles.contains(&((*pos).into())) {
-                // if it was not visible before, make it appear and describe in gamelog
-                if *vis == Visibility::Hidden {
-                    *vis = Visibility::Visible;
-                    // if enemy, get name update gamelog
-                    if let Ok(name) = names_enemies_q.get(ent) {
-                        let message = format!("{} appears.\n", name.0);
-                        gamelog.add_entry(message);
-                    }
-                    // if item, provide hint
-                    if let Ok(name) = names_items_q.get(ent) {
-                        let message = format!("{}. Press G to grab.\n", name.0);
-                        gamelog.add_entry(message);
-                    }
-                }
-            } else {
-                // otherwise make it invisible
-                *vis = Visibility::Hidden;
-            }
+            // otherwise make it invisible
+            *vis = Visibility::Hidden;
         }
     }
 }
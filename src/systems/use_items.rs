@@ -7,7 +7,8 @@ pub fn use_items(
     item_messages: Query<(Entity, &ActivateItem)>,
     healing_query: Query<(Entity, &ProvidesHealing)>,
     mapping_query: Query<(Entity, &ProvidesDungeonMap)>,
-    mut maptiles_query: Query<(Entity, &mut Visibility), With<MapTile>>,
+    lights_query: Query<&LightSource>,
+    mut mb: ResMut<MapBuilder>,
     names_query: Query<&Naming>,
 ) {
     // for every message
@@ -27,9 +28,10 @@ pub fn use_items(
         // if it is a map item
         if let Ok(_) = mapping_query.get(activated_item.item) {
             // reveal all tiles
-            maptiles_query
+            mb.map
+                .revealed
                 .iter_mut()
-                .for_each(|(_, mut vis)| *vis = Visibility::Visible);
+                .for_each(|revealed| *revealed = true);
             // update gamelog
             let message = format!("Map revealed.\n");
             gamelog.add_entry(message);
@@ -37,6 +39,14 @@ pub fn use_items(
 
         // delete the message
         commands.entity(message_entity).despawn();
+
+        // lights are not used up, they shine while carried
+        if lights_query.get(activated_item.item).is_ok() {
+            let name = names_query.get(activated_item.item).unwrap();
+            let message = format!("{} lights the way.\n", name.0);
+            gamelog.add_entry(message);
+            continue;
+        }
         // remove the item
         commands.entity(activated_item.item).despawn_recursive();
     }
@@ -0,0 +1,175 @@
+use crate::prelude::*;
+use ron::de::from_bytes;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+// a sprite sheet, and where every logical sprite id is found in it
+#[derive(Clone, Deserialize, Debug)]
+pub struct Tileset {
+    pub name: String,
+    // path of the image, relative to the assets folder
+    pub image: String,
+    pub tile_size: (f32, f32),
+    pub columns: usize,
+    pub rows: usize,
+    // glyphs are white and colored by whoever draws them, instead of having their own colors
+    pub tinted: bool,
+    pub sprites: HashMap<String, usize>,
+}
+
+// sprite used when a tileset doesn't know an id
+const UNKNOWN_SPRITE: &str = "unknown";
+
+impl Tileset {
+    pub fn index(&self, sprite: &str) -> usize {
+        self.sprites
+            .get(sprite)
+            .or_else(|| self.sprites.get(UNKNOWN_SPRITE))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // graphical tiles keep their own colors
+    pub fn tint(&self, color: Color) -> Color {
+        if self.tinted {
+            color
+        } else {
+            Color::WHITE.with_a(color.a())
+        }
+    }
+
+    pub fn sprite(&self, sprite: &str, color: Color) -> TextureAtlasSprite {
+        TextureAtlasSprite {
+            custom_size: Some(Vec2::new(1.0, 1.0)),
+            index: self.index(sprite),
+            color: self.tint(color),
+            ..Default::default()
+        }
+    }
+}
+
+// the logical sprite of an entity and its color, to draw it again when the tileset changes
+#[derive(Component, Clone)]
+pub struct TileSprite {
+    pub id: String,
+    pub color: Color,
+}
+
+impl TileSprite {
+    pub fn new(id: &str, color: Color) -> Self {
+        Self {
+            id: id.to_string(),
+            color,
+        }
+    }
+}
+
+// all the tilesets in the tilesets folder, and the one in use
+#[derive(Resource)]
+pub struct Tilesets {
+    pub sets: Vec<Tileset>,
+    pub current: usize,
+}
+
+// the files are built into the game, a new tileset has to be listed here
+static TILESETS: &[(&str, &[u8])] = &[
+    ("ascii.ron", include_bytes!("../assets/tilesets/ascii.ron")),
+    (
+        "pixel16.ron",
+        include_bytes!("../assets/tilesets/pixel16.ron"),
+    ),
+];
+// tileset used when the game starts
+const DEFAULT_TILESET: &str = "ASCII";
+
+impl Tilesets {
+    pub fn load() -> Self {
+        let sets: Vec<Tileset> = TILESETS
+            .iter()
+            .map(|(name, bytes)| {
+                from_bytes(bytes)
+                    .unwrap_or_else(|e| panic!("Unable to load tileset {}: {}", name, e))
+            })
+            .collect();
+        let current = sets
+            .iter()
+            .position(|tileset| tileset.name == DEFAULT_TILESET)
+            .unwrap_or_else(|| panic!("There is no tileset {}.", DEFAULT_TILESET));
+
+        // every tileset should draw the same sprites as the default one
+        for tileset in &sets {
+            for id in sets[current].sprites.keys() {
+                if !tileset.sprites.contains_key(id) {
+                    println!("Warning: tileset {} has no sprite {}", tileset.name, id);
+                }
+            }
+        }
+
+        Self { sets, current }
+    }
+
+    pub fn current(&self) -> &Tileset {
+        &self.sets[self.current]
+    }
+
+    pub fn next(&mut self) {
+        self.current = (self.current + 1) % self.sets.len();
+    }
+
+    // loads the image of the current tileset and cuts it in a texture atlas
+    pub fn charset(
+        &self,
+        asset_server: &AssetServer,
+        texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> CharsetAsset {
+        let tileset = self.current();
+        let texture: Handle<Image> = asset_server.load(tileset.image.clone());
+        let atlas = TextureAtlas::from_grid(
+            texture.clone(),
+            Vec2::new(tileset.tile_size.0, tileset.tile_size.1),
+            tileset.columns,
+            tileset.rows,
+            None,
+            None,
+        );
+        CharsetAsset {
+            atlas: texture_atlases.add(atlas),
+            texture,
+        }
+    }
+}
+
+// draws every sprite again with the tileset in use
+fn apply_tileset(
+    mut commands: Commands,
+    tilesets: Res<Tilesets>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut sprites_q: Query<(
+        &TileSprite,
+        &mut TextureAtlasSprite,
+        &mut Handle<TextureAtlas>,
+    )>,
+) {
+    let charset = tilesets.charset(&asset_server, &mut texture_atlases);
+    let tileset = tilesets.current();
+    for (tile_sprite, mut sprite, mut atlas) in sprites_q.iter_mut() {
+        sprite.index = tileset.index(&tile_sprite.id);
+        // the alpha belongs to the lighting
+        sprite.color = tileset.tint(tile_sprite.color).with_a(sprite.color.a());
+        *atlas = charset.atlas.clone();
+    }
+    commands.insert_resource(charset);
+}
+
+pub struct TilesetPlugin;
+impl Plugin for TilesetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (apply_tileset, retile_map)
+                .chain()
+                .run_if(resource_changed::<Tilesets>().and_then(not(resource_added::<Tilesets>()))),
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use super::splash_screen::start_screen_input;
+use crate::prelude::*;
+
+// the key showing the controls from the start screen
+pub(super) const CONTROLS_KEY: KeyCode = KeyCode::C;
+// width of the column with the names of the actions
+const NAME_WIDTH: usize = 18;
+
+#[derive(Component)]
+struct ControlsUI;
+
+fn spawn_controls(commands: &mut Commands, font_manager: &FontManager, bindings: &KeyBindings) {
+    let style = |font_size: f32, color: Color| TextStyle {
+        font: font_manager.font.clone(),
+        font_size,
+        color,
+    };
+
+    let sections: Vec<TextSection> = bindings
+        .describe()
+        .into_iter()
+        .flat_map(|(name, keys)| {
+            [
+                TextSection::new(
+                    format!("{:<1$}", name, NAME_WIDTH),
+                    style(20.0, Color::GOLD),
+                ),
+                TextSection::new(format!("{}\n", keys), style(20.0, Color::WHITE)),
+            ]
+        })
+        .collect();
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..Default::default()
+                },
+                background_color: BackgroundColor(Color::rgb(0.0, 0.0, 0.0)),
+                ..Default::default()
+            },
+            ControlsUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Controls\n",
+                style(50.0, Color::GOLD),
+            ));
+            parent.spawn(TextBundle::from_sections(sections));
+            parent.spawn(TextBundle::from_section(
+                "\nPress any key to go back.",
+                style(20.0, Color::WHITE),
+            ));
+        });
+}
+
+// on the start screen, C shows the controls, and then any key goes back
+fn controls_input(
+    mut commands: Commands,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    font_manager: Res<FontManager>,
+    bindings: Res<KeyBindings>,
+    controls_q: Query<Entity, With<ControlsUI>>,
+) {
+    if let Ok(controls) = controls_q.get_single() {
+        if keyboard_input.get_just_pressed().next().is_some() {
+            commands.entity(controls).despawn_recursive();
+            keyboard_input.reset_all();
+        }
+    } else if keyboard_input.just_pressed(CONTROLS_KEY) {
+        spawn_controls(&mut commands, &font_manager, &bindings);
+        keyboard_input.reset_all();
+    }
+}
+
+fn despawn_controls(mut commands: Commands, controls_q: Query<Entity, With<ControlsUI>>) {
+    for e in controls_q.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
+
+pub struct ControlsPlugin;
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            controls_input
+                .before(start_screen_input)
+                .run_if(in_state(TurnState::StartScreen)),
+        )
+        .add_systems(OnExit(TurnState::StartScreen), despawn_controls);
+    }
+}
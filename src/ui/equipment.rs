@@ -59,58 +59,14 @@ fn update_equipment_text(
     }
 }
 
-fn equip_weapon(
-    mut commands: Commands,
-    mut highlighted_item: ResMut<popup::HighlightedItem>,
-    mut chosen_item: EventReader<popup::ChosenItemEvent>,
-    player_query: Query<Entity, With<Player>>,
-    items_query: Query<(Entity, &Carried), With<Weapon>>,
-    equipped_query: Query<(Entity, &Equipped)>,
-    mut turn_state: ResMut<NextState<TurnState>>,
-    mut popup_state: ResMut<NextState<PopUpState>>,
-) {
-    // if user selected an item, then it will have a number over 0, otherwise -1
-    let mut selected_item = -1;
-    for se in chosen_item.read() {
-        selected_item = se.0 as i32;
-    }
-
-    // get player entity, we will need it to filter out items carried by player
-    let player_ent = player_query.single();
-
-    // get the item entity selected by the player
-    let item_entity = items_query
-        .iter()
-        .filter(|(_, carried)| carried.0 == player_ent)
-        .enumerate()
-        .filter(|(item_count, (_, _))| *item_count as i32 == selected_item)
-        .find_map(|(_, (item_entity, _))| Some(item_entity));
-
-    // if the item exists, remove equipped from other weapons, and equip this one
-    if let Some(item_entity) = item_entity {
-        // remove equipped component from every weapon equipped (should be only 1)
-        equipped_query.iter().for_each(|(equipped_weapon, _)| {
-            commands.entity(equipped_weapon).remove::<Equipped>();
-        });
-        commands.entity(item_entity).insert(Equipped);
-
-        // set also highlighted item to 0
-        highlighted_item.0 = 0;
-
-        // after using an item, move turn state and disable popup
-        turn_state.set(TurnState::PlayerTurn);
-        popup_state.set(PopUpState::None);
-    }
-}
-
 pub struct EquipmentPlugin;
 impl Plugin for EquipmentPlugin {
     fn build(&self, app: &mut App) {
         app
-            // listening to user input on inventory screen
+            // keeping the list up to date while the screen is open
             .add_systems(
                 Update,
-                (equip_weapon, update_equipment_text).run_if(in_state(PopUpState::EquipmentPopup)),
+                update_equipment_text.run_if(in_state(PopUpState::EquipmentPopup)),
             );
     }
 }
@@ -16,7 +16,16 @@ struct InventoryText;
 #[derive(Component)]
 struct DungeonLevelText;
 
-fn bottom_hud(mut commands: Commands, font_manager: Res<FontManager>) {
+// "(I)nventory" when its key is the initial, "Inventory (K)" otherwise
+fn key_label(name: &str, keys: &[KeyCode]) -> String {
+    match keys.first().map(|key| format!("{:?}", key)) {
+        Some(key) if name.starts_with(&key) => format!("({}){}", key, &name[key.len()..]),
+        Some(key) => format!("{} ({})", name, key),
+        None => name.to_string(),
+    }
+}
+
+fn bottom_hud(mut commands: Commands, font_manager: Res<FontManager>, bindings: Res<KeyBindings>) {
     commands
         // root node, just a black rectangle where the UI will be
         .spawn((
@@ -273,7 +282,11 @@ fn bottom_hud(mut commands: Commands, font_manager: Res<FontManager>) {
                                                         ..Default::default()
                                                     },
                                                     text: Text::from_section(
-                                                        "(I)nventory".to_string(),
+                                                        key_label(
+                                                            "Inventory",
+                                                            bindings
+                                                                .keys(PlayerAction::OpenInventory),
+                                                        ),
                                                         TextStyle {
                                                             font_size: 20.0,
                                                             font: font_manager.font.clone(),
@@ -315,7 +328,11 @@ fn bottom_hud(mut commands: Commands, font_manager: Res<FontManager>) {
                                                         ..Default::default()
                                                     },
                                                     text: Text::from_section(
-                                                        "(E)quipment".to_string(),
+                                                        key_label(
+                                                            "Equipment",
+                                                            bindings
+                                                                .keys(PlayerAction::OpenEquipment),
+                                                        ),
                                                         TextStyle {
                                                             font_size: 20.0,
                                                             font: font_manager.font.clone(),
@@ -397,14 +414,18 @@ fn update_game_log(game_log: Res<GameLog>, mut text_query: Query<&mut Text, With
 }
 
 fn update_dungeonleveltext(
-    player_q: Query<&Player>,
+    graph: Res<DungeonGraph>,
     mut text_query: Query<&mut Text, Added<DungeonLevelText>>,
 ) {
-    let level = player_q.single().map_level;
+    let level = graph.current;
 
-    // update dungeon level text
+    // update dungeon level text, side branches show their own name and depth
     for mut text in text_query.iter_mut() {
-        text.sections[0].value = format!("Dungeon Level: {}", level + 1);
+        text.sections[0].value = if level.branch == MAIN_BRANCH {
+            format!("Dungeon Level: {}", level.depth + 1)
+        } else {
+            format!("{}: {}", graph.name(level.branch), level.depth + 1)
+        };
     }
 }
 
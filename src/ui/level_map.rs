@@ -0,0 +1,126 @@
+use crate::prelude::*;
+
+#[derive(Component)]
+struct LevelMapUI;
+
+// character and color used to draw a tile on the level map
+fn tile_symbol(tile: TileType) -> (char, Color) {
+    match tile {
+        TileType::Wall => ('#', Color::rgb(0.6, 0.6, 0.6)),
+        TileType::Floor => ('.', Color::rgb(0.3, 0.3, 0.3)),
+        TileType::Door => ('+', Color::rgb(0.6, 0.4, 0.2)),
+        TileType::Exit | TileType::BranchStairs => ('>', Color::GOLD),
+        TileType::UpStairs => ('<', Color::GOLD),
+        TileType::Void => (' ', Color::BLACK),
+    }
+}
+
+// full screen map with every tile the player has revealed in this level
+fn level_map_ui(
+    mut commands: Commands,
+    font_manager: Res<FontManager>,
+    mb: Res<MapBuilder>,
+    bindings: Res<KeyBindings>,
+    player_q: Query<&Position, With<Player>>,
+) {
+    let player_pos = player_q.single();
+    let style = |color: Color| TextStyle {
+        font: font_manager.font.clone(),
+        font_size: 9.0,
+        color,
+    };
+
+    // one section for every run of tiles drawn with the same color. Rows go from top to bottom
+    let mut sections: Vec<TextSection> = Vec::new();
+    for y in (0..SCREEN_HEIGHT).rev() {
+        for x in 0..SCREEN_WIDTH {
+            let idx = map_idx(x, y);
+            let (symbol, color) = if x == player_pos.x && y == player_pos.y {
+                ('@', Color::YELLOW)
+            } else if mb.map.revealed[idx] {
+                tile_symbol(mb.map.tiles[idx])
+            } else {
+                (' ', Color::BLACK)
+            };
+
+            match sections.last_mut() {
+                Some(section) if section.style.color == color => section.value.push(symbol),
+                _ => sections.push(TextSection::new(symbol.to_string(), style(color))),
+            }
+        }
+        if let Some(section) = sections.last_mut() {
+            section.value.push('\n');
+        }
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..Default::default()
+                },
+                background_color: BackgroundColor(Color::rgb(0.0, 0.0, 0.0)),
+                ..Default::default()
+            },
+            LevelMapUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Level Map",
+                TextStyle {
+                    font: font_manager.font.clone(),
+                    font_size: 30.0,
+                    color: Color::GOLD,
+                },
+            ));
+            parent.spawn(TextBundle::from_sections(sections));
+            let close_keys = bindings
+                .menu_close
+                .iter()
+                .chain(bindings.keys(PlayerAction::OpenMap));
+            parent.spawn(TextBundle::from_section(
+                format!("Press {} to close.", key_names(close_keys, " or ")),
+                style(Color::WHITE),
+            ));
+        });
+}
+
+fn level_map_input(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut actions: EventWriter<PlayerAction>,
+) {
+    // the keys closing menus, or the one that opened the map
+    let close_keys = bindings
+        .menu_close
+        .iter()
+        .chain(bindings.keys(PlayerAction::OpenMap));
+    if keyboard_input.any_just_pressed(close_keys.copied()) {
+        actions.send(PlayerAction::CloseMenu);
+        keyboard_input.reset_all();
+    }
+}
+
+fn despawn_level_map(mut commands: Commands, level_map_q: Query<Entity, With<LevelMapUI>>) {
+    for e in level_map_q.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
+
+pub struct LevelMapPlugin;
+impl Plugin for LevelMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(PopUpState::LevelMap), level_map_ui)
+            .add_systems(
+                Update,
+                level_map_input.run_if(in_state(PopUpState::LevelMap)),
+            )
+            .add_systems(OnExit(PopUpState::LevelMap), despawn_level_map);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::prelude::*;
+
+// color of the cursor, see through so the tile shows below
+const CURSOR_COLOR: Color = Color::rgba(1.0, 1.0, 0.0, 0.4);
+
+#[derive(Component)]
+struct LookUI;
+
+#[derive(Component)]
+struct LookText;
+
+fn tile_name(tile: TileType) -> &'static str {
+    match tile {
+        TileType::Wall => "Wall",
+        TileType::Floor => "Floor",
+        TileType::Door => "Door",
+        TileType::Exit => "Stairs down",
+        TileType::UpStairs => "Stairs up",
+        TileType::BranchStairs => "Stairs to a side branch",
+        TileType::Void => "Nothing",
+    }
+}
+
+// the cursor starts on the player, and the side panel shows what is under it
+fn look_ui(
+    mut commands: Commands,
+    font_manager: Res<FontManager>,
+    atlas: Res<CharsetAsset>,
+    tilesets: Res<Tilesets>,
+    player_q: Query<&Position, With<Player>>,
+) {
+    let player_pos = player_q.single();
+    let tile_sprite = TileSprite {
+        id: "cursor".to_string(),
+        color: CURSOR_COLOR,
+    };
+    commands.spawn((
+        SpriteSheetBundle {
+            texture_atlas: atlas.atlas.clone(),
+            sprite: tilesets
+                .current()
+                .sprite(&tile_sprite.id, tile_sprite.color),
+            ..Default::default()
+        },
+        tile_sprite,
+        LookCursor,
+        Position {
+            x: player_pos.x,
+            y: player_pos.y,
+            z: 3,
+        },
+        TileSize::square(1.0),
+    ));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(30.),
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(0.0),
+                    top: Val::Px(0.0),
+                    // above the bottom hud
+                    bottom: Val::Px(100.0),
+                    border: UiRect::all(Val::Px(5.0)),
+                    ..Default::default()
+                },
+                background_color: BackgroundColor(Color::rgb(0.65, 0.65, 0.65)),
+                ..Default::default()
+            },
+            LookUI,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..Default::default()
+                    },
+                    background_color: BackgroundColor(Color::rgb(0.15, 0.15, 0.15)),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section(
+                            " ",
+                            TextStyle {
+                                font: font_manager.font.clone(),
+                                font_size: 20.0,
+                                color: Color::WHITE,
+                            },
+                        ),
+                        LookText,
+                    ));
+                });
+        });
+}
+
+// move keys move the cursor to the next revealed tile that way, the keys closing menus or the
+// look key go back to the game
+fn look_input(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mb: Res<MapBuilder>,
+    mut actions: EventWriter<PlayerAction>,
+    mut cursor_q: Query<&mut Position, With<LookCursor>>,
+) {
+    let key = match keyboard_input.get_just_pressed().next() {
+        Some(key) => *key,
+        None => return,
+    };
+    if bindings.menu_close.contains(&key) || bindings.keys(PlayerAction::Look).contains(&key) {
+        actions.send(PlayerAction::CloseMenu);
+        keyboard_input.reset_all();
+        return;
+    }
+    if let Some(PlayerAction::Move(dx, dy)) = bindings.action(key) {
+        for mut pos in cursor_q.iter_mut() {
+            let next = (1..)
+                .map(|step| Position::new(pos.x + dx * step, pos.y + dy * step, pos.z))
+                .take_while(|next| mb.map.in_bounds(*next))
+                .find(|next| mb.map.revealed[map_idx(next.x, next.y)]);
+            if let Some(next) = next {
+                *pos = next;
+            }
+        }
+    }
+}
+
+// what the player knows about the tile under the cursor. Only what is in view can be
+// examined, remembered tiles just show what they are
+fn update_look_text(
+    font_manager: Res<FontManager>,
+    mb: Res<MapBuilder>,
+    cursor_q: Query<&Position, (With<LookCursor>, Changed<Position>)>,
+    mut text_q: Query<&mut Text, With<LookText>>,
+    player_q: Query<(Entity, &FieldOfView), With<Player>>,
+    things_q: Query<
+        (
+            Entity,
+            &Position,
+            &Naming,
+            Option<&Description>,
+            Option<&Health>,
+            Option<&Damage>,
+        ),
+        Without<Carried>,
+    >,
+    weapons_q: Query<(&Naming, &Carried, Option<&Damage>), (With<Weapon>, With<Equipped>)>,
+) {
+    let cursor = match cursor_q.get_single() {
+        Ok(cursor) => *cursor,
+        Err(_) => return,
+    };
+    let (player, player_fov) = player_q.single();
+    let line = |value: String, font_size: f32, color: Color| {
+        TextSection::new(
+            format!("{}\n", value),
+            TextStyle {
+                font: font_manager.font.clone(),
+                font_size,
+                color,
+            },
+        )
+    };
+
+    let mut sections = Vec::new();
+    let idx = map_idx(cursor.x, cursor.y);
+    let in_view = player_fov.visible_tiles.contains(&cursor.into());
+    sections.push(line(
+        tile_name(mb.map.tiles[idx]).to_string(),
+        30.0,
+        Color::GOLD,
+    ));
+    let seen = if in_view {
+        "In view."
+    } else {
+        "Remembered, out of view."
+    };
+    sections.push(line(seen.to_string(), 20.0, Color::GRAY));
+
+    let things = things_q
+        .iter()
+        .filter(|(entity, pos, ..)| **pos == cursor && (in_view || *entity == player));
+    for (entity, _, name, description, health, damage) in things {
+        sections.push(line(String::new(), 20.0, Color::WHITE));
+        sections.push(line(name.0.clone(), 25.0, Color::WHITE));
+        if let Some(description) = description {
+            sections.push(line(description.0.clone(), 20.0, Color::GRAY));
+        }
+        if let Some(health) = health {
+            let hp = format!("HP: {} / {}", health.current, health.max);
+            sections.push(line(hp, 20.0, Color::WHITE));
+        }
+        if entity == player {
+            let weapon = weapons_q
+                .iter()
+                .find(|(_, carried, _)| carried.0 == player)
+                .map_or("none".to_string(), |(weapon, _, damage)| match damage {
+                    Some(damage) => format!("{}, damage {}", weapon.0, damage.0),
+                    None => weapon.0.clone(),
+                });
+            sections.push(line(format!("Weapon: {}", weapon), 20.0, Color::WHITE));
+        } else if let Some(damage) = damage {
+            let damage = format!("Damage: {}", damage.0);
+            sections.push(line(damage, 20.0, Color::WHITE));
+        }
+    }
+
+    for mut text in text_q.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+fn despawn_look(
+    mut commands: Commands,
+    look_ui_q: Query<Entity, With<LookUI>>,
+    cursor_q: Query<Entity, With<LookCursor>>,
+) {
+    for e in look_ui_q.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+    for e in cursor_q.iter() {
+        commands.entity(e).despawn();
+    }
+}
+
+pub struct LookPlugin;
+impl Plugin for LookPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(TurnState::Looking), look_ui)
+            .add_systems(
+                Update,
+                (look_input, update_look_text)
+                    .chain()
+                    .run_if(in_state(TurnState::Looking)),
+            )
+            .add_systems(OnExit(TurnState::Looking), despawn_look);
+    }
+}
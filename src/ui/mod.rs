@@ -1,11 +1,15 @@
 use crate::prelude::*;
 
+mod controls;
 mod equipment;
 mod hud;
 mod inventory;
+mod level_map;
+mod look;
 mod popup;
 mod splash_screen;
 mod tooltips;
+mod travel;
 
 #[derive(Component)]
 pub struct TopUINode;
@@ -26,8 +30,12 @@ impl Plugin for UIPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup)
             .add_plugins(splash_screen::MenuPlugin)
+            .add_plugins(controls::ControlsPlugin)
             .add_plugins(hud::HudPlugin)
             .add_plugins(popup::PopUpPlugin)
-            .add_plugins(tooltips::TooltipsPlugin);
+            .add_plugins(level_map::LevelMapPlugin)
+            .add_plugins(look::LookPlugin)
+            .add_plugins(tooltips::TooltipsPlugin)
+            .add_plugins(travel::TravelPlugin);
     }
 }
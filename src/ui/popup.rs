@@ -11,9 +11,6 @@ pub struct InventoryText;
 #[derive(Component)]
 pub struct DescriptionText;
 
-#[derive(Event)]
-pub struct ChosenItemEvent(pub i32);
-
 #[derive(Resource)]
 pub struct HighlightedItem(pub i32);
 
@@ -197,12 +194,11 @@ fn popup_ui(
 }
 
 fn player_input(
-    mut chosen_item: EventWriter<ChosenItemEvent>,
+    mut actions: EventWriter<PlayerAction>,
     mut highlighted_item: ResMut<HighlightedItem>,
     mut keyboard_input: ResMut<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
     popup_currentstate: ResMut<State<PopUpState>>,
-    mut popup_nextstate: ResMut<NextState<PopUpState>>,
-    mut turn_nextstate: ResMut<NextState<TurnState>>,
     player_items: Query<(Entity, &Carried), Without<Weapon>>,
     player_weapons: Query<(Entity, &Carried), With<Weapon>>,
 ) {
@@ -218,26 +214,22 @@ fn player_input(
 
     let key = keyboard_input.get_pressed().next().cloned();
     if let Some(key) = key {
-        match key {
-            KeyCode::Escape => {
-                // close inventory window
-                // update state
-                popup_nextstate.set(PopUpState::None);
-                turn_nextstate.set(TurnState::AwaitingInput);
-            }
-            KeyCode::Return => {
-                // activate selected item and close inventory window
-                chosen_item.send(ChosenItemEvent(highlighted_item.0));
-            }
-            KeyCode::Up => {
-                // move to previous item in list
-                highlighted_item.0 = i32::max(0, highlighted_item.0 - 1);
+        if bindings.menu_close.contains(&key) {
+            // close inventory window
+            actions.send(PlayerAction::CloseMenu);
+        } else if bindings.menu_select.contains(&key) {
+            // activate selected item, or equip it, and close the window
+            if *popup_currentstate.get() == PopUpState::InventoryPopup {
+                actions.send(PlayerAction::UseItem(highlighted_item.0));
+            } else {
+                actions.send(PlayerAction::Equip(highlighted_item.0));
             }
-            KeyCode::Down => {
-                // move to next item in list
-                highlighted_item.0 = i32::min(list_len as i32, highlighted_item.0 + 1);
-            }
-            _ => (),
+        } else if bindings.menu_up.contains(&key) {
+            // move to previous item in list
+            highlighted_item.0 = i32::max(0, highlighted_item.0 - 1);
+        } else if bindings.menu_down.contains(&key) {
+            // move to next item in list
+            highlighted_item.0 = i32::min(list_len as i32, highlighted_item.0 + 1);
         }
         keyboard_input.reset(key);
     }
@@ -250,11 +242,15 @@ fn despawn_menu(mut commands: Commands, query_inventory: Query<Entity, With<Inve
     }
 }
 
+// the list may have changed next time it opens, so start from the top
+fn reset_highlighted_item(mut highlighted_item: ResMut<HighlightedItem>) {
+    highlighted_item.0 = 0;
+}
+
 pub struct PopUpPlugin;
 impl Plugin for PopUpPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<ChosenItemEvent>()
-            .insert_resource(HighlightedItem(0))
+        app.insert_resource(HighlightedItem(0))
             .add_plugins(inventory::InventoryPlugin)
             .add_plugins(equipment::EquipmentPlugin)
             // listening to user input on inventory screen
@@ -267,8 +263,14 @@ impl Plugin for PopUpPlugin {
                 player_input.run_if(in_state(PopUpState::EquipmentPopup)),
             )
             // cleanup when exiting
-            .add_systems(OnExit(PopUpState::InventoryPopup), despawn_menu)
-            .add_systems(OnExit(PopUpState::EquipmentPopup), despawn_menu)
+            .add_systems(
+                OnExit(PopUpState::InventoryPopup),
+                (despawn_menu, reset_highlighted_item),
+            )
+            .add_systems(
+                OnExit(PopUpState::EquipmentPopup),
+                (despawn_menu, reset_highlighted_item),
+            )
             // creating when entering
             .add_systems(OnEnter(PopUpState::InventoryPopup), popup_ui)
             .add_systems(OnEnter(PopUpState::EquipmentPopup), popup_ui);
@@ -1,3 +1,4 @@
+use super::controls::CONTROLS_KEY;
 use crate::prelude::*;
 
 #[derive(Component)]
@@ -10,6 +11,7 @@ fn splash_screen(
     mut commands: Commands,
     font_manager: Res<FontManager>,
     turn_state: Res<State<TurnState>>,
+    transition: Res<LevelTransition>,
     top_ui_node_q: Query<Entity, With<TopUINode>>,
 ) {
     // If we are not in StartScreen we need to remove ALL the other UI stuff around the game
@@ -40,14 +42,24 @@ fn splash_screen(
                 title_color = Color::RED;
             } else if *turn_state.get() == TurnState::Victory {
                 title = "You win!";
+            } else if *turn_state.get() == TurnState::NextLevel && transition.going_up {
+                title = "Back Upstairs";
             } else if *turn_state.get() == TurnState::NextLevel {
                 title = "Level Completed";
             }
 
+            // the start screen also tells how to see the controls
+            let mut hint = "\nPress any key to start game.".to_string();
+            let mut height = 140.;
+            if *turn_state.get() == TurnState::StartScreen {
+                hint.push_str(&format!("\nPress {:?} to see the controls.", CONTROLS_KEY));
+                height += 40.;
+            }
+
             // Spawn menu text
             parent.spawn(TextBundle {
                 style: Style {
-                    height: Val::Px(140. * 1.),
+                    height: Val::Px(height),
                     margin: UiRect {
                         left: Val::Auto,
                         right: Val::Auto,
@@ -69,7 +81,7 @@ fn splash_screen(
                             },
                         },
                         TextSection {
-                            value: "\nPress any key to start game.".to_string(),
+                            value: hint,
                             style: TextStyle {
                                 font: font_manager.font.clone(),
                                 font_size: 40.0,
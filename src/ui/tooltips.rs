@@ -70,6 +70,29 @@ fn hide_tooltip(
     }
 }
 
+// the map tile under the mouse cursor, if it is in the window
+pub(super) fn cursor_grid_position(
+    wnd: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Option<Position> {
+    let pos = wnd.cursor_position()?;
+    let tile_size_x = wnd.width() / SCREEN_WIDTH as f32;
+    let tile_size_y = wnd.height() / SCREEN_HEIGHT as f32;
+
+    // apply the camera transform
+    let point_wld = camera.viewport_to_world_2d(camera_transform, pos)?;
+
+    // transform world coordinates to our grid
+    let grid_x = (point_wld.x / tile_size_x) + (SCREEN_WIDTH / 2) as f32;
+    let grid_y = (point_wld.y / tile_size_y) + (SCREEN_HEIGHT / 2) as f32 - (UI_HEIGHT / 2) as f32;
+    Some(Position {
+        x: grid_x as i32,
+        y: grid_y as i32,
+        z: 0,
+    })
+}
+
 // when user left clicks, update tooltip and make it visible
 fn update_tooltip(
     // need to get window dimensions
@@ -93,27 +116,14 @@ fn update_tooltip(
         // get the primary window
         let wnd = wnds.get_single().unwrap();
 
-        // check if the cursor is in the primary window
-        if let Some(pos) = wnd.cursor_position() {
-            // assuming there is exactly one main camera entity, so this is OK
-            let (camera, camera_transform) = q_camera.single();
-
-            let tile_size_x = wnd.width() / SCREEN_WIDTH as f32;
-            let tile_size_y = wnd.height() / SCREEN_HEIGHT as f32;
-
-            // apply the camera transform
-            let point_wld = camera.viewport_to_world_2d(camera_transform, pos).unwrap();
-
-            // transform world coordinates to our grid
-            let grid_x = (point_wld.x / tile_size_x) + (SCREEN_WIDTH / 2) as f32;
-            let grid_y =
-                (point_wld.y / tile_size_y) + (SCREEN_HEIGHT / 2) as f32 - (UI_HEIGHT / 2) as f32;
-            let grid_position = Position {
-                x: grid_x as i32,
-                y: grid_y as i32,
-                z: 0,
-            };
+        // assuming there is exactly one main camera entity, so this is OK
+        let (camera, camera_transform) = q_camera.single();
 
+        // check if the cursor is in the primary window
+        if let (Some(pos), Some(grid_position)) = (
+            wnd.cursor_position(),
+            cursor_grid_position(wnd, camera, camera_transform),
+        ) {
             // now we go through all the entities with name to see which one is the nearest
             // some variables placeholders to save the entity name and its health
             let mut good_click = false;
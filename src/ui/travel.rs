@@ -0,0 +1,116 @@
+use super::tooltips::cursor_grid_position;
+use crate::prelude::*;
+
+// color of the path marks, see through so the map shows below
+const PATH_COLOR: Color = Color::rgba(1.0, 1.0, 0.0, 0.6);
+
+// the tile under the cursor, when the player could travel there
+fn hovered_tile(
+    wnds: &Query<&Window, With<PrimaryWindow>>,
+    q_camera: &Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) -> Option<Position> {
+    let wnd = wnds.get_single().ok()?;
+    let (camera, camera_transform) = q_camera.get_single().ok()?;
+    cursor_grid_position(wnd, camera, camera_transform)
+}
+
+// a left click on a revealed tile walks the player there. Clicking an enemy in view only
+// shows its tooltip
+fn travel_input(
+    buttons: Res<Input<MouseButton>>,
+    wnds: Query<&Window, With<PrimaryWindow>>,
+    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mb: Res<MapBuilder>,
+    mut actions: EventWriter<PlayerAction>,
+    player_q: Query<(&Position, &FieldOfView), With<Player>>,
+    enemies_q: Query<&Position, With<Enemy>>,
+) {
+    if !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let destination = match hovered_tile(&wnds, &q_camera) {
+        Some(destination) => destination,
+        None => return,
+    };
+    let (player_pos, fov) = player_q.single();
+    let enemy_there = enemies_q
+        .iter()
+        .any(|pos| *pos == destination && fov.visible_tiles.contains(&(destination.into())));
+    if !enemy_there && travel_path(&mb.map, *player_pos, destination).is_some() {
+        actions.send(PlayerAction::Travel(destination.x, destination.y));
+    }
+}
+
+// marks the path to the tile under the cursor, drawn again when the cursor or the player
+// moves. Nothing is shown out of the game, like in menus or between levels
+fn preview_path(
+    mut commands: Commands,
+    mut shown: Local<Option<(Position, Position)>>,
+    turn_state: Res<State<TurnState>>,
+    wnds: Query<&Window, With<PrimaryWindow>>,
+    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mb: Option<Res<MapBuilder>>,
+    atlas: Res<CharsetAsset>,
+    tilesets: Res<Tilesets>,
+    player_q: Query<&Position, With<Player>>,
+    steps_q: Query<Entity, With<PathStep>>,
+) {
+    let in_game = matches!(
+        turn_state.get(),
+        TurnState::AwaitingInput | TurnState::PlayerTurn | TurnState::MonsterTurn
+    );
+    let wanted = match (
+        in_game,
+        player_q.get_single(),
+        hovered_tile(&wnds, &q_camera),
+    ) {
+        (true, Ok(player_pos), Some(cursor)) => Some((*player_pos, cursor)),
+        _ => None,
+    };
+    if wanted == *shown {
+        return;
+    }
+    *shown = wanted;
+
+    for entity in steps_q.iter() {
+        commands.entity(entity).despawn();
+    }
+    let path = match (wanted, mb) {
+        (Some((player_pos, cursor)), Some(mb)) => travel_path(&mb.map, player_pos, cursor),
+        _ => None,
+    };
+    for step in path.unwrap_or_default() {
+        let tile_sprite = TileSprite {
+            id: "path".to_string(),
+            color: PATH_COLOR,
+        };
+        commands.spawn((
+            SpriteSheetBundle {
+                texture_atlas: atlas.atlas.clone(),
+                sprite: tilesets
+                    .current()
+                    .sprite(&tile_sprite.id, tile_sprite.color),
+                ..Default::default()
+            },
+            tile_sprite,
+            PathStep,
+            Position {
+                x: step.x,
+                y: step.y,
+                z: 1,
+            },
+            TileSize::square(1.0),
+        ));
+    }
+}
+
+pub struct TravelPlugin;
+impl Plugin for TravelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            travel_input.run_if(in_state(TurnState::AwaitingInput)),
+        )
+        .add_systems(Update, preview_path);
+    }
+}